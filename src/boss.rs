@@ -0,0 +1,71 @@
+// 現在の幕のボス・取得済みのボスレリック・鍵の状況を扱うモジュール
+use libsts::Save;
+use serde_json::{json, Value};
+
+// 鍵の表示順(ルビー・エメラルド・サファイア)、持っていなければ"-"
+fn keys(save: &Save) -> [Option<&'static str>; 3] {
+    [
+        save.has_ruby_key.then_some("R"),
+        save.has_emerald_key.then_some("E"),
+        save.has_sapphire_key.then_some("S"),
+    ]
+}
+
+/* sts_boss.txtの内容
+
+  Act 2 boss: The Champ; keys: R E -    <- 鍵は4幕目ありのrunのみ
+  Boss relics: Act 1 Black Star, Act 2 skipped
+*/
+pub fn compose_txt_boss(save: &Save) -> String {
+    let mut ret = format!("Act {} boss: {}", save.act_num, save.boss);
+    if save.is_final_act_on {
+        let keys: Vec<&str> = keys(save).iter().map(|key| key.unwrap_or("-")).collect();
+        ret.push_str(&format!("; keys: {}", keys.join(" ")));
+    }
+    ret.push('\n');
+    let relics: Vec<String> = save.metric_boss_relics.iter()
+                                                     .enumerate()
+                                                     .map(|(n, choice)| format!("Act {} {}", n + 1, choice.picked.as_deref().unwrap_or("skipped")))
+                                                     .collect();
+    if relics.is_empty() {
+        ret.push_str("Boss relics: none yet\n");
+    } else {
+        ret.push_str(&format!("Boss relics: {}\n", relics.join(", ")));
+    }
+    ret
+}
+
+pub fn compose_json_boss(save: &Save) -> Value {
+    json!({
+        "act": save.act_num,
+        "boss": save.boss,
+        "boss_relics": save.metric_boss_relics.iter().map(|choice| &choice.picked).collect::<Vec<_>>(),
+        "final_act": save.is_final_act_on,
+        "keys": { "ruby": save.has_ruby_key, "emerald": save.has_emerald_key, "sapphire": save.has_sapphire_key },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture::*;
+    use libsts::BossRelicChoice;
+
+    #[test]
+    fn shows_current_boss_relics_and_keys() {
+        let mut save = example_save();
+        assert_eq!(compose_txt_boss(&save), "Act 1 boss: Slime Boss\nBoss relics: none yet\n");
+
+        save.act_num = 3;
+        save.boss = "The Champ".to_string();
+        save.is_final_act_on = true;
+        save.has_ruby_key = true;
+        save.has_emerald_key = true;
+        save.metric_boss_relics = vec![
+            BossRelicChoice { picked: Some("Black Star".into()), not_picked: vec!["Ectoplasm".into()] },
+            BossRelicChoice { picked: None, not_picked: vec!["Runic Dome".into()] },
+        ];
+        assert_eq!(compose_txt_boss(&save),
+                   "Act 3 boss: The Champ; keys: R E -\nBoss relics: Act 1 Black Star, Act 2 skipped\n");
+    }
+}
@@ -0,0 +1,253 @@
+// カード報酬の選択(metric_card_choices)を扱うモジュール
+//
+// 現在のrunの選択はtxt/JSONに書き出し、runごとの選択をデータディレクトリに蓄積して
+// キャラクターごとのピック率を集計する
+use libsts::{CardChoice, Save};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::common::*;
+use crate::modifiers::*;
+use crate::state::*;
+use crate::system::*;
+
+const HISTORY_FILE_NAME: &str = "card_choices.json";
+const DEFAULT_CARD_CHOICES_SHOWN: usize = 5;
+// ピック率のレポートに載せるカードの数
+const PICK_RATES_SHOWN: usize = 20;
+// 何も取らなかった場合のpicked
+const SKIP: &str = "SKIP";
+
+// 報酬画面1回分の選択
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChoiceRecord {
+    pub floor: u32,
+    pub picked: Option<String>, // スキップならNone
+    pub not_picked: Vec<String>,
+}
+impl ChoiceRecord {
+    pub fn from_metric(choice: &CardChoice) -> Self {
+        Self {
+            floor: choice.floor as u32,
+            picked: Some(choice.picked.clone()).filter(|picked| picked != SKIP),
+            not_picked: choice.not_picked.clone(),
+        }
+    }
+}
+
+pub fn choices_of(save: &Save) -> Vec<ChoiceRecord> {
+    save.metric_card_choices.iter().map(ChoiceRecord::from_metric).collect()
+}
+
+// カードごとの提示回数と取得回数
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickRate {
+    pub card: String,
+    pub offered: u32,
+    pub picked: u32,
+}
+
+// 履歴ファイルに保存するrun1回分の選択
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct RunChoices {
+    character: String,
+    // デイリーやカスタムのrunは通常のrunと分けて集計する
+    daily: bool,
+    custom: bool,
+    mods: Vec<String>,
+    choices: Vec<ChoiceRecord>,
+}
+
+// 履歴ファイルの中身(キーはシード文字列)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct History {
+    runs: BTreeMap<String, RunChoices>,
+}
+
+impl RunChoices {
+    fn kind(&self) -> RunKind {
+        match (self.daily, self.custom) {
+            (true, _) => RunKind::Daily,
+            (false, true) => RunKind::Custom,
+            (false, false) => RunKind::Standard,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CardChoiceHistory {
+    path: PathBuf,
+    history: History,
+    write_stats: WriteStats,
+}
+impl CardChoiceHistory {
+    pub fn load(fs: &impl FileSystem, json_data: &JsonData) -> Self {
+        let path = data_dir_path(json_data).join(HISTORY_FILE_NAME);
+        let history = match fs.read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring broken card choice history {}: {}", path.display(), e);
+                History::default()
+            }),
+            Err(_) => History::default(),
+        };
+        Self { path, history, write_stats: WriteStats::default() }
+    }
+
+    // runの選択を記録して、変わっていれば保存する
+    pub fn record_run(&mut self, fs: &impl FileSystem, seed: &str, character: &str, tag: &RunTag, choices: &[ChoiceRecord]) {
+        if choices.is_empty() {
+            return;
+        }
+        let run = RunChoices {
+            character: character.to_string(),
+            daily: tag.kind == RunKind::Daily,
+            custom: tag.kind == RunKind::Custom,
+            mods: tag.mods.clone(),
+            choices: choices.to_vec(),
+        };
+        if self.history.runs.get(seed) == Some(&run) {
+            return;
+        }
+        self.history.runs.insert(seed.to_string(), run);
+        match serde_json::to_string_pretty(&self.history) {
+            Ok(body) => {
+                let result = fs.write_atomic(&self.path, body.as_bytes());
+                self.write_stats.record("card choice history", result);
+            },
+            Err(e) => error!("Failed to serialize card choice history: {}", e),
+        }
+    }
+
+    // 前回取り出してからの書き出し結果
+    pub fn take_write_stats(&mut self) -> WriteStats {
+        std::mem::take(&mut self.write_stats)
+    }
+
+    pub fn runs_of(&self, character: &str, kind: RunKind) -> usize {
+        self.history.runs.values().filter(|run| run.character == character && run.kind() == kind).count()
+    }
+    // キャラクターの全runでのピック率(提示回数の多い順、アップグレードは区別しない)
+    // 種類の違うrun(通常/デイリー/カスタム)は混ぜない
+    pub fn pick_rates(&self, character: &str, kind: RunKind) -> Vec<PickRate> {
+        let mut counts: BTreeMap<&str, (u32, u32)> = BTreeMap::new();
+        let runs = self.history.runs.values().filter(|run| run.character == character && run.kind() == kind);
+        for choice in runs.flat_map(|run| &run.choices) {
+            if let Some(picked) = &choice.picked {
+                let count = counts.entry(base_card_id(picked)).or_default();
+                count.0 += 1;
+                count.1 += 1;
+            }
+            for card in &choice.not_picked {
+                counts.entry(base_card_id(card)).or_default().0 += 1;
+            }
+        }
+        let mut rates: Vec<PickRate> = counts.into_iter()
+                                             .map(|(card, (offered, picked))| PickRate { card: card.to_string(), offered, picked })
+                                             .collect();
+        rates.sort_by_key(|rate| std::cmp::Reverse(rate.offered));
+        rates
+    }
+}
+
+// "Inflame+1" -> "Inflame"
+fn base_card_id(card: &str) -> &str {
+    match card.rsplit_once('+') {
+        Some((base, level)) if !base.is_empty() && level.bytes().all(|b| b.is_ascii_digit()) => base,
+        _ => card,
+    }
+}
+
+pub fn card_choices_shown(json_data: &JsonData) -> usize {
+    json_data.get_value_from_key("card_choices_shown").unwrap_or(DEFAULT_CARD_CHOICES_SHOWN)
+}
+
+// 直近の報酬画面(新しい順) 例: "Floor 7: picked Inflame over Clash, Warcry"
+pub fn compose_txt_card_choices(choices: &[ChoiceRecord], shown: usize) -> String {
+    if choices.is_empty() {
+        return String::from("No card rewards yet\n");
+    }
+    let mut ret = String::new();
+    for choice in choices.iter().rev().take(shown) {
+        let others = choice.not_picked.join(", ");
+        let line = match (&choice.picked, others.is_empty()) {
+            (Some(picked), true) => format!("Floor {}: picked {}\n", choice.floor, picked),
+            (Some(picked), false) => format!("Floor {}: picked {} over {}\n", choice.floor, picked, others),
+            (None, _) => format!("Floor {}: skipped {}\n", choice.floor, others),
+        };
+        ret.push_str(&line);
+    }
+    ret
+}
+
+pub fn compose_json_card_choices(seed: &str, character: &str, choices: &[ChoiceRecord]) -> Value {
+    json!({ "seed": seed, "character": character, "choices": choices })
+}
+
+// 例: "Inflame: 3/5 (60%)"
+pub fn compose_txt_pick_rates(character: &str, kind: RunKind, runs: usize, rates: &[PickRate]) -> String {
+    let mut ret = format!("{}{} pick rates ({} runs)\n", character, kind.label(), runs);
+    for rate in rates.iter().take(PICK_RATES_SHOWN) {
+        let percent = rate.picked * 100 / rate.offered.max(1);
+        ret.push_str(&format!("{}: {}/{} ({}%)\n", rate.card, rate.picked, rate.offered, percent));
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fake::*;
+
+    fn choice(floor: u32, picked: Option<&str>, not_picked: &[&str]) -> ChoiceRecord {
+        ChoiceRecord {
+            floor,
+            picked: picked.map(String::from),
+            not_picked: not_picked.iter().map(|card| card.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn lists_latest_reward_screens_first() {
+        let choices = [
+            choice(1, Some("Anger"), &["Clash", "Havoc"]),
+            choice(3, None, &["Clash", "Warcry"]),
+            choice(7, Some("Inflame"), &["Clash", "Warcry"]),
+        ];
+        assert_eq!(compose_txt_card_choices(&choices, 2),
+                   "Floor 7: picked Inflame over Clash, Warcry\nFloor 3: skipped Clash, Warcry\n");
+        assert_eq!(compose_txt_card_choices(&[], 2), "No card rewards yet\n");
+    }
+
+    fn tag(kind: RunKind) -> RunTag { RunTag { kind, mods: Vec::new() } }
+
+    #[test]
+    fn aggregates_pick_rates_across_runs() {
+        let fs = MemFileSystem::default();
+        let json_data = JsonData::from_value(serde_json::json!({ "data_dir_path": "/data/" }));
+        let mut history = CardChoiceHistory::load(&fs, &json_data);
+        history.record_run(&fs, "AAA", "IRONCLAD", &tag(RunKind::Standard), &[choice(1, Some("Clash"), &["Anger", "Inflame"])]);
+        history.record_run(&fs, "BBB", "IRONCLAD", &tag(RunKind::Standard), &[choice(1, Some("Clash+1"), &["Anger"])]);
+        history.record_run(&fs, "CCC", "DEFECT", &tag(RunKind::Standard), &[choice(1, Some("Zap"), &["Clash"])]);
+        history.record_run(&fs, "DDD", "IRONCLAD", &tag(RunKind::Daily), &[choice(1, Some("Anger"), &["Clash"])]);
+        history.record_run(&fs, "EEE", "IRONCLAD", &tag(RunKind::Custom), &[choice(1, Some("Havoc"), &["Clash"])]);
+
+        // 保存した履歴を読み直しても同じ集計になる
+        let history = CardChoiceHistory::load(&fs, &json_data);
+        assert_eq!(history.runs_of("IRONCLAD", RunKind::Standard), 2);
+        assert_eq!(history.pick_rates("IRONCLAD", RunKind::Standard), [
+            PickRate { card: "Anger".into(), offered: 2, picked: 0 },
+            PickRate { card: "Clash".into(), offered: 2, picked: 2 },
+            PickRate { card: "Inflame".into(), offered: 1, picked: 0 },
+        ]);
+        assert_eq!(compose_txt_pick_rates("IRONCLAD", RunKind::Standard, 2, &history.pick_rates("IRONCLAD", RunKind::Standard)[1..2]),
+                   "IRONCLAD pick rates (2 runs)\nClash: 2/2 (100%)\n");
+        // デイリーやカスタムのrunは別に集計する
+        assert_eq!(history.runs_of("IRONCLAD", RunKind::Custom), 1);
+        assert_eq!(compose_txt_pick_rates("IRONCLAD", RunKind::Daily, 1, &history.pick_rates("IRONCLAD", RunKind::Daily)),
+                   "IRONCLAD Daily Climb pick rates (1 runs)\nAnger: 1/1 (100%)\nClash: 0/1 (0%)\n");
+    }
+}
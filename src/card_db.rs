@@ -0,0 +1,167 @@
+// カードの種類とコストの表(autosaveにはカードIDしかないので統計用に持つ)
+//
+// コスト: -1はX、-2は使用不可(呪い・状態異常など)
+// IDはゲーム内部のもので、表示名と異なるものがある(例: "Underhanded Strike"はSneaky Strike)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CardType {
+    Attack,
+    Skill,
+    Power,
+    Curse,
+    Status,
+}
+
+pub const X_COST: i8 = -1;
+pub const UNPLAYABLE: i8 = -2;
+
+// (ID, 種類, コスト, アップグレード後のコスト)
+#[rustfmt::skip]
+const CARDS: &[(&str, CardType, i8, i8)] = &[
+    // Ironclad
+    ("Strike_R", CardType::Attack, 1, 1), ("Bash", CardType::Attack, 2, 2), ("Anger", CardType::Attack, 0, 0),
+    ("Body Slam", CardType::Attack, 1, 0), ("Clash", CardType::Attack, 0, 0), ("Cleave", CardType::Attack, 1, 1),
+    ("Clothesline", CardType::Attack, 2, 2), ("Headbutt", CardType::Attack, 1, 1), ("Heavy Blade", CardType::Attack, 2, 2),
+    ("Iron Wave", CardType::Attack, 1, 1), ("Perfected Strike", CardType::Attack, 2, 2), ("Pommel Strike", CardType::Attack, 1, 1),
+    ("Sword Boomerang", CardType::Attack, 1, 1), ("Thunderclap", CardType::Attack, 1, 1), ("Twin Strike", CardType::Attack, 1, 1),
+    ("Wild Strike", CardType::Attack, 1, 1), ("Blood for Blood", CardType::Attack, 4, 3), ("Carnage", CardType::Attack, 2, 2),
+    ("Dropkick", CardType::Attack, 1, 1), ("Hemokinesis", CardType::Attack, 1, 1), ("Pummel", CardType::Attack, 1, 1),
+    ("Rampage", CardType::Attack, 1, 1), ("Reckless Charge", CardType::Attack, 0, 0), ("Searing Blow", CardType::Attack, 2, 2),
+    ("Sever Soul", CardType::Attack, 2, 2), ("Uppercut", CardType::Attack, 2, 2), ("Whirlwind", CardType::Attack, X_COST, X_COST),
+    ("Bludgeon", CardType::Attack, 3, 3), ("Feed", CardType::Attack, 1, 1), ("Fiend Fire", CardType::Attack, 2, 2),
+    ("Immolate", CardType::Attack, 2, 2), ("Reaper", CardType::Attack, 2, 2),
+    ("Defend_R", CardType::Skill, 1, 1), ("Armaments", CardType::Skill, 1, 1), ("Flex", CardType::Skill, 0, 0),
+    ("Havoc", CardType::Skill, 1, 0), ("Shrug It Off", CardType::Skill, 1, 1), ("True Grit", CardType::Skill, 1, 1),
+    ("Warcry", CardType::Skill, 0, 0), ("Battle Trance", CardType::Skill, 0, 0), ("Bloodletting", CardType::Skill, 0, 0),
+    ("Burning Pact", CardType::Skill, 1, 1), ("Disarm", CardType::Skill, 1, 1), ("Dual Wield", CardType::Skill, 1, 1),
+    ("Entrench", CardType::Skill, 2, 1), ("Flame Barrier", CardType::Skill, 2, 2), ("Ghostly Armor", CardType::Skill, 1, 1),
+    ("Infernal Blade", CardType::Skill, 1, 0), ("Intimidate", CardType::Skill, 0, 0), ("Power Through", CardType::Skill, 1, 1),
+    ("Rage", CardType::Skill, 0, 0), ("Second Wind", CardType::Skill, 1, 1), ("Seeing Red", CardType::Skill, 1, 0),
+    ("Sentinel", CardType::Skill, 1, 1), ("Shockwave", CardType::Skill, 2, 2), ("Spot Weakness", CardType::Skill, 1, 1),
+    ("Double Tap", CardType::Skill, 1, 1), ("Exhume", CardType::Skill, 1, 0), ("Impervious", CardType::Skill, 2, 2),
+    ("Limit Break", CardType::Skill, 1, 1), ("Offering", CardType::Skill, 0, 0),
+    ("Combust", CardType::Power, 1, 1), ("Dark Embrace", CardType::Power, 2, 1), ("Evolve", CardType::Power, 1, 1),
+    ("Feel No Pain", CardType::Power, 1, 1), ("Fire Breathing", CardType::Power, 1, 1), ("Inflame", CardType::Power, 1, 1),
+    ("Metallicize", CardType::Power, 1, 1), ("Rupture", CardType::Power, 1, 1), ("Barricade", CardType::Power, 3, 2),
+    ("Berserk", CardType::Power, 0, 0), ("Brutality", CardType::Power, 0, 0), ("Corruption", CardType::Power, 3, 2),
+    ("Demon Form", CardType::Power, 3, 3), ("Juggernaut", CardType::Power, 2, 2),
+    // Silent
+    ("Strike_G", CardType::Attack, 1, 1), ("Neutralize", CardType::Attack, 0, 0), ("Bane", CardType::Attack, 1, 1),
+    ("Dagger Spray", CardType::Attack, 1, 1), ("Dagger Throw", CardType::Attack, 1, 1), ("Flying Knee", CardType::Attack, 1, 1),
+    ("Poisoned Stab", CardType::Attack, 1, 1), ("Quick Slash", CardType::Attack, 1, 1), ("Slice", CardType::Attack, 0, 0),
+    ("Underhanded Strike", CardType::Attack, 2, 2), ("Sucker Punch", CardType::Attack, 1, 1), ("All Out Attack", CardType::Attack, 1, 1),
+    ("Backstab", CardType::Attack, 0, 0), ("Choke", CardType::Attack, 2, 2), ("Dash", CardType::Attack, 2, 2),
+    ("Endless Agony", CardType::Attack, 0, 0), ("Eviscerate", CardType::Attack, 3, 3), ("Finisher", CardType::Attack, 1, 1),
+    ("Flechettes", CardType::Attack, 1, 1), ("Heel Hook", CardType::Attack, 1, 1), ("Masterful Stab", CardType::Attack, 0, 0),
+    ("Predator", CardType::Attack, 2, 2), ("Riddle With Holes", CardType::Attack, 2, 2), ("Skewer", CardType::Attack, X_COST, X_COST),
+    ("Die Die Die", CardType::Attack, 1, 1), ("Glass Knife", CardType::Attack, 1, 1), ("Grand Finale", CardType::Attack, 0, 0),
+    ("Unload", CardType::Attack, 1, 1),
+    ("Defend_G", CardType::Skill, 1, 1), ("Survivor", CardType::Skill, 1, 1), ("Acrobatics", CardType::Skill, 1, 1),
+    ("Backflip", CardType::Skill, 1, 1), ("Blade Dance", CardType::Skill, 1, 1), ("Cloak And Dagger", CardType::Skill, 1, 1),
+    ("Deadly Poison", CardType::Skill, 1, 1), ("Deflect", CardType::Skill, 0, 0), ("Dodge and Roll", CardType::Skill, 1, 1),
+    ("Outmaneuver", CardType::Skill, 1, 1), ("PiercingWail", CardType::Skill, 1, 1), ("Prepared", CardType::Skill, 0, 0),
+    ("Blur", CardType::Skill, 1, 1), ("Bouncing Flask", CardType::Skill, 2, 2), ("Calculated Gamble", CardType::Skill, 0, 0),
+    ("Catalyst", CardType::Skill, 1, 1), ("Concentrate", CardType::Skill, 0, 0), ("Crippling Poison", CardType::Skill, 2, 2),
+    ("Distraction", CardType::Skill, 1, 0), ("Escape Plan", CardType::Skill, 0, 0), ("Expertise", CardType::Skill, 1, 1),
+    ("Leg Sweep", CardType::Skill, 2, 2), ("Reflex", CardType::Skill, UNPLAYABLE, UNPLAYABLE), ("Setup", CardType::Skill, 1, 0),
+    ("Tactician", CardType::Skill, UNPLAYABLE, UNPLAYABLE), ("Terror", CardType::Skill, 1, 0), ("Adrenaline", CardType::Skill, 0, 0),
+    ("Venomology", CardType::Skill, 1, 0), ("Bullet Time", CardType::Skill, 3, 2), ("Burst", CardType::Skill, 1, 1),
+    ("Corpse Explosion", CardType::Skill, 2, 2), ("Doppelganger", CardType::Skill, X_COST, X_COST), ("Malaise", CardType::Skill, X_COST, X_COST),
+    ("Night Terror", CardType::Skill, 3, 2), ("Phantasmal Killer", CardType::Skill, 1, 0), ("Storm of Steel", CardType::Skill, 1, 1),
+    ("Accuracy", CardType::Power, 1, 1), ("Caltrops", CardType::Power, 1, 1), ("Footwork", CardType::Power, 1, 1),
+    ("Infinite Blades", CardType::Power, 1, 1), ("Noxious Fumes", CardType::Power, 1, 1), ("Well Laid Plans", CardType::Power, 1, 1),
+    ("A Thousand Cuts", CardType::Power, 2, 2), ("After Image", CardType::Power, 1, 1), ("Envenom", CardType::Power, 2, 1),
+    ("Tools of the Trade", CardType::Power, 1, 0), ("Wraith Form v2", CardType::Power, 3, 3),
+    // Defect
+    ("Strike_B", CardType::Attack, 1, 1), ("Ball Lightning", CardType::Attack, 1, 1), ("Barrage", CardType::Attack, 1, 1),
+    ("Beam Cell", CardType::Attack, 0, 0), ("Cold Snap", CardType::Attack, 1, 1), ("Compile Driver", CardType::Attack, 1, 1),
+    ("Gash", CardType::Attack, 0, 0), ("Go for the Eyes", CardType::Attack, 0, 0), ("Rebound", CardType::Attack, 1, 1),
+    ("Streamline", CardType::Attack, 2, 2), ("Sweeping Beam", CardType::Attack, 1, 1), ("Blizzard", CardType::Attack, 1, 1),
+    ("Doom and Gloom", CardType::Attack, 2, 2), ("FTL", CardType::Attack, 0, 0), ("Lockon", CardType::Attack, 1, 1),
+    ("Melter", CardType::Attack, 1, 1), ("Rip and Tear", CardType::Attack, 1, 1), ("Scrape", CardType::Attack, 1, 1),
+    ("Sunder", CardType::Attack, 3, 3), ("All For One", CardType::Attack, 2, 2), ("Core Surge", CardType::Attack, 1, 1),
+    ("Hyperbeam", CardType::Attack, 2, 2), ("Meteor Strike", CardType::Attack, 5, 5), ("Thunder Strike", CardType::Attack, 3, 3),
+    ("Defend_B", CardType::Skill, 1, 1), ("Zap", CardType::Skill, 1, 0), ("Dualcast", CardType::Skill, 1, 0),
+    ("Conserve Battery", CardType::Skill, 1, 1), ("Coolheaded", CardType::Skill, 1, 1), ("Hologram", CardType::Skill, 1, 1),
+    ("Leap", CardType::Skill, 1, 1), ("Redo", CardType::Skill, 1, 0), ("Stack", CardType::Skill, 1, 1),
+    ("Steam", CardType::Skill, 0, 0), ("Turbo", CardType::Skill, 0, 0), ("Aggregate", CardType::Skill, 1, 1),
+    ("Auto Shields", CardType::Skill, 1, 1), ("BootSequence", CardType::Skill, 0, 0), ("Chaos", CardType::Skill, 1, 1),
+    ("Chill", CardType::Skill, 0, 0), ("Consume", CardType::Skill, 2, 2), ("Darkness", CardType::Skill, 1, 1),
+    ("Double Energy", CardType::Skill, 1, 0), ("Undo", CardType::Skill, 2, 2), ("Force Field", CardType::Skill, 4, 4),
+    ("Fusion", CardType::Skill, 2, 1), ("Genetic Algorithm", CardType::Skill, 1, 1), ("Glacier", CardType::Skill, 2, 2),
+    ("Steam Power", CardType::Skill, 0, 0), ("Recycle", CardType::Skill, 1, 0), ("Reinforced Body", CardType::Skill, X_COST, X_COST),
+    ("Reprogram", CardType::Skill, 1, 1), ("Skim", CardType::Skill, 1, 1), ("Tempest", CardType::Skill, X_COST, X_COST),
+    ("White Noise", CardType::Skill, 1, 0), ("Amplify", CardType::Skill, 1, 1), ("Fission", CardType::Skill, 0, 0),
+    ("Multi-Cast", CardType::Skill, X_COST, X_COST), ("Rainbow", CardType::Skill, 2, 2), ("Reboot", CardType::Skill, 0, 0),
+    ("Seek", CardType::Skill, 0, 0),
+    ("Capacitor", CardType::Power, 1, 1), ("Defragment", CardType::Power, 1, 1), ("Heatsinks", CardType::Power, 1, 1),
+    ("Hello World", CardType::Power, 1, 1), ("Loop", CardType::Power, 1, 1), ("Self Repair", CardType::Power, 1, 1),
+    ("Static Discharge", CardType::Power, 1, 1), ("Storm", CardType::Power, 1, 1), ("Biased Cognition", CardType::Power, 1, 1),
+    ("Buffer", CardType::Power, 2, 2), ("Creative AI", CardType::Power, 3, 2), ("Echo Form", CardType::Power, 3, 3),
+    ("Electrodynamics", CardType::Power, 2, 2), ("Machine Learning", CardType::Power, 1, 1),
+    // Watcher
+    ("Strike_P", CardType::Attack, 1, 1), ("Eruption", CardType::Attack, 2, 1), ("BowlingBash", CardType::Attack, 1, 1),
+    ("Consecrate", CardType::Attack, 0, 0), ("CrushJoints", CardType::Attack, 1, 1), ("CutThroughFate", CardType::Attack, 1, 1),
+    ("EmptyFist", CardType::Attack, 1, 1), ("FlurryOfBlows", CardType::Attack, 0, 0), ("FlyingSleeves", CardType::Attack, 1, 1),
+    ("FollowUp", CardType::Attack, 1, 1), ("JustLucky", CardType::Attack, 0, 0), ("SashWhip", CardType::Attack, 1, 1),
+    ("Conclude", CardType::Attack, 1, 1), ("CarveReality", CardType::Attack, 1, 1), ("FearNoEvil", CardType::Attack, 1, 1),
+    ("ReachHeaven", CardType::Attack, 2, 2), ("SandsOfTime", CardType::Attack, 4, 4), ("SignatureMove", CardType::Attack, 2, 2),
+    ("TalkToTheHand", CardType::Attack, 1, 1), ("Tantrum", CardType::Attack, 1, 1), ("Wallop", CardType::Attack, 2, 2),
+    ("Weave", CardType::Attack, 0, 0), ("WheelKick", CardType::Attack, 2, 2), ("WindmillStrike", CardType::Attack, 2, 2),
+    ("Ragnarok", CardType::Attack, 3, 3), ("LessonLearned", CardType::Attack, 2, 2), ("Brilliance", CardType::Attack, 1, 1),
+    ("Smite", CardType::Attack, 1, 1), ("ThroughViolence", CardType::Attack, 1, 1), ("Expunger", CardType::Attack, 1, 1),
+    ("Defend_P", CardType::Skill, 1, 1), ("Vigilance", CardType::Skill, 2, 2), ("Crescendo", CardType::Skill, 1, 0),
+    ("EmptyBody", CardType::Skill, 1, 1), ("Evaluate", CardType::Skill, 1, 1), ("Halt", CardType::Skill, 0, 0),
+    ("PathToVictory", CardType::Skill, 1, 1), ("Prostrate", CardType::Skill, 0, 0), ("Protect", CardType::Skill, 2, 2),
+    ("ThirdEye", CardType::Skill, 1, 1), ("ClearTheMind", CardType::Skill, 1, 0), ("Collect", CardType::Skill, X_COST, X_COST),
+    ("DeceiveReality", CardType::Skill, 1, 1), ("EmptyMind", CardType::Skill, 1, 1), ("Indignation", CardType::Skill, 1, 1),
+    ("InnerPeace", CardType::Skill, 1, 1), ("Meditate", CardType::Skill, 1, 1), ("Perseverance", CardType::Skill, 2, 2),
+    ("Pray", CardType::Skill, 1, 1), ("Sanctity", CardType::Skill, 1, 1), ("Swivel", CardType::Skill, 2, 2),
+    ("Vengeance", CardType::Skill, 1, 1), ("WaveOfTheHand", CardType::Skill, 1, 1), ("Worship", CardType::Skill, 2, 2),
+    ("WreathOfFlame", CardType::Skill, 1, 1), ("Alpha", CardType::Skill, 1, 1), ("Blasphemy", CardType::Skill, 1, 1),
+    ("ConjureBlade", CardType::Skill, X_COST, X_COST), ("DeusExMachina", CardType::Skill, UNPLAYABLE, UNPLAYABLE),
+    ("Judgement", CardType::Skill, 1, 1), ("Omniscience", CardType::Skill, 4, 3), ("Scrawl", CardType::Skill, 1, 0),
+    ("SpiritShield", CardType::Skill, 2, 2), ("Vault", CardType::Skill, 3, 2), ("Wish", CardType::Skill, 3, 3),
+    ("Insight", CardType::Skill, 0, 0), ("Miracle", CardType::Skill, 0, 0), ("Safety", CardType::Skill, 1, 1),
+    ("Beta", CardType::Skill, 2, 1),
+    ("Adaptation", CardType::Power, 1, 0), ("BattleHymn", CardType::Power, 1, 1), ("Fasting2", CardType::Power, 2, 2),
+    ("Wireheading", CardType::Power, 1, 1), ("LikeWater", CardType::Power, 1, 1), ("MentalFortress", CardType::Power, 1, 1),
+    ("Nirvana", CardType::Power, 1, 1), ("Study", CardType::Power, 2, 1), ("DevaForm", CardType::Power, 3, 3),
+    ("Devotion", CardType::Power, 1, 1), ("Establishment", CardType::Power, 1, 1), ("MasterReality", CardType::Power, 1, 1),
+    ("Omega", CardType::Power, 3, 3),
+    // Colorless
+    ("Dramatic Entrance", CardType::Attack, 0, 0), ("Flash of Steel", CardType::Attack, 0, 0), ("Mind Blast", CardType::Attack, 2, 1),
+    ("Swift Strike", CardType::Attack, 0, 0), ("HandOfGreed", CardType::Attack, 2, 2), ("Bite", CardType::Attack, 1, 1),
+    ("RitualDagger", CardType::Attack, 1, 1), ("Shiv", CardType::Attack, 0, 0),
+    ("Bandage Up", CardType::Skill, 0, 0), ("Blind", CardType::Skill, 0, 0), ("Dark Shackles", CardType::Skill, 0, 0),
+    ("Deep Breath", CardType::Skill, 0, 0), ("Discovery", CardType::Skill, 1, 1), ("Enlightenment", CardType::Skill, 0, 0),
+    ("Finesse", CardType::Skill, 0, 0), ("Forethought", CardType::Skill, 0, 0), ("Good Instincts", CardType::Skill, 0, 0),
+    ("Impatience", CardType::Skill, 0, 0), ("Jack Of All Trades", CardType::Skill, 0, 0), ("Madness", CardType::Skill, 1, 0),
+    ("Panacea", CardType::Skill, 0, 0), ("PanicButton", CardType::Skill, 0, 0), ("Purity", CardType::Skill, 0, 0),
+    ("Trip", CardType::Skill, 0, 0), ("Apotheosis", CardType::Skill, 2, 1), ("Chrysalis", CardType::Skill, 2, 2),
+    ("Master of Strategy", CardType::Skill, 0, 0), ("Metamorphosis", CardType::Skill, 2, 2), ("Secret Technique", CardType::Skill, 0, 0),
+    ("Secret Weapon", CardType::Skill, 0, 0), ("The Bomb", CardType::Skill, 2, 2), ("Thinking Ahead", CardType::Skill, 0, 0),
+    ("Transmutation", CardType::Skill, X_COST, X_COST), ("Violence", CardType::Skill, 0, 0), ("Ghostly", CardType::Skill, 1, 1),
+    ("J.A.X.", CardType::Skill, 0, 0),
+    ("Magnetism", CardType::Power, 2, 1), ("Mayhem", CardType::Power, 2, 1), ("Panache", CardType::Power, 0, 0),
+    ("Sadistic Nature", CardType::Power, 0, 0),
+    // Curse
+    ("AscendersBane", CardType::Curse, UNPLAYABLE, UNPLAYABLE), ("Clumsy", CardType::Curse, UNPLAYABLE, UNPLAYABLE),
+    ("CurseOfTheBell", CardType::Curse, UNPLAYABLE, UNPLAYABLE), ("Decay", CardType::Curse, UNPLAYABLE, UNPLAYABLE),
+    ("Doubt", CardType::Curse, UNPLAYABLE, UNPLAYABLE), ("Injury", CardType::Curse, UNPLAYABLE, UNPLAYABLE),
+    ("Necronomicurse", CardType::Curse, UNPLAYABLE, UNPLAYABLE), ("Normality", CardType::Curse, UNPLAYABLE, UNPLAYABLE),
+    ("Pain", CardType::Curse, UNPLAYABLE, UNPLAYABLE), ("Parasite", CardType::Curse, UNPLAYABLE, UNPLAYABLE),
+    ("Pride", CardType::Curse, 1, 1), ("Regret", CardType::Curse, UNPLAYABLE, UNPLAYABLE),
+    ("Shame", CardType::Curse, UNPLAYABLE, UNPLAYABLE), ("Writhe", CardType::Curse, UNPLAYABLE, UNPLAYABLE),
+    // Status
+    ("Burn", CardType::Status, UNPLAYABLE, UNPLAYABLE), ("Dazed", CardType::Status, UNPLAYABLE, UNPLAYABLE),
+    ("Slimed", CardType::Status, 1, 1), ("Wound", CardType::Status, UNPLAYABLE, UNPLAYABLE),
+    ("Void", CardType::Status, UNPLAYABLE, UNPLAYABLE),
+];
+
+// カードの種類とコスト(表にないカードはNone)
+pub fn card_info(id: &str, upgrades: u32) -> Option<(CardType, i8)> {
+    CARDS.iter()
+         .find(|(card_id, ..)| *card_id == id)
+         .map(|(_, card_type, cost, upgraded_cost)| (*card_type, if upgrades > 0 { *upgraded_cost } else { *cost }))
+}
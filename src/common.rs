@@ -1,19 +1,38 @@
 // libsts::Saveの各フィールドはここ参照
 // https://docs.rs/libsts/latest/libsts/save/struct.Save.html
 use libsts::Save;
-use serde::de::Error as SerdeError;
-use serde_json::{json, Error, Value};
+use serde::Deserialize;
+use serde_json::{Error, Value};
 use std::ffi::OsStr;
-use std::fs::{self};
 use std::path::{Path, PathBuf};
 
+use crate::boss::*;
+use crate::card_choices::*;
+use crate::daily::*;
+use crate::deck_stats::*;
+use crate::diff::*;
+use crate::dirs::*;
+use crate::economy::*;
+use crate::events::*;
+use crate::logger::Level;
+use crate::map::*;
 use crate::mode::*;
+use crate::modifiers::*;
+use crate::preferences::*;
+use crate::process::*;
+use crate::seed::*;
+use crate::streak::*;
+use crate::state::*;
+use crate::system::*;
+use crate::timeline::*;
+use crate::timer::*;
 
 // 更新日時比較時の条件分岐用
 pub enum ModifiedTimeStatus {
     New,
     Old,
     Equal,
+    Missing, // 更新日時を取得できない(比較中に削除された等)
 }
 
 // 監視対象(autosave)のパラメータを格納する構造体
@@ -24,6 +43,7 @@ pub struct Target {
     full_path: String,
     character_type: String,
     modified_time: u64,
+    write_stats: WriteStats,
 }
 #[rustfmt::skip]
 impl Target {
@@ -34,126 +54,358 @@ impl Target {
             full_path: String::new(),
             character_type: String::new(),
             modified_time: 0,
+            write_stats: WriteStats::default(),
         }
     }
     pub fn init_dir_path(&mut self, json_data: &JsonData) {
-        self.autosave_dir_path = json_data.get_value_from_key("autosave_dir_path").unwrap();
-        self.write_txt_dir_path = json_data.get_value_from_key("write_txt_dir_path").unwrap();
+        self.autosave_dir_path = json_data.get_path_from_key("autosave_dir_path").unwrap();
+        self.write_txt_dir_path = write_txt_dir_path(json_data);
     }
     // getter
     pub fn full_path(&self) -> String { self.full_path.clone() }
     pub fn autosave_dir_path(&self) -> PathBuf { self.autosave_dir_path.clone() }
     pub fn character_type(&self) -> String { self.character_type.clone() }
     pub fn modified_time(&self) -> u64 { self.modified_time }
+    pub fn write_stats(&self) -> &WriteStats { &self.write_stats }
+    // 状態ファイルなどtxt以外の書き出し結果も同じカウンタにまとめる
+    pub fn merge_write_stats(&mut self, stats: WriteStats) { self.write_stats.merge(stats); }
 
-    pub fn autosave_exists(&self) -> bool {
+    // 既定の出力先はまだ無いことがあるので作っておく
+    pub fn create_write_txt_dir(&self, fs: &impl FileSystem) {
+        if let Err(e) = fs.create_dir_all(Path::new(&self.write_txt_dir_path)) {
+            error!("Failed to create output directory {}: {}", self.write_txt_dir_path, e);
+        }
+    }
+
+    pub fn autosave_exists(&self, fs: &impl FileSystem) -> bool {
         // 監視中のautosaveファイルの有無を調べる
-        Path::new(&self.full_path).try_exists().unwrap()
+        fs.is_file(Path::new(&self.full_path))
     }
-    pub fn update_params<P: AsRef<Path>>(&mut self, autosave_path: &P) {
+    pub fn update_params<P: AsRef<Path>>(&mut self, fs: &impl FileSystem, autosave_path: &P) {
         self.full_path = to_string(autosave_path.as_ref());
         self.character_type = get_file_basename(autosave_path.as_ref());
-        self.modified_time = get_file_modified_time(autosave_path).unwrap();
+        self.modified_time = get_file_modified_time(fs, autosave_path).unwrap_or(self.modified_time);
     }
     #[allow(dead_code)]
-    pub fn write_autosave(&self, save: &Save) {
+    pub fn write_autosave(&mut self, fs: &impl FileSystem, save: &Save) {
         // Get the base64 string representation of our modified savefile
         if let Ok(modified_save) = &save.to_b64_string() {
-            let _ = fs::write(&self.full_path, modified_save);
-            println!("Save succeeded: autosave");
+            let result = fs.write_atomic(Path::new(&self.full_path), modified_save.as_bytes());
+            self.write_stats.record("autosave", result);
         }
     }
     #[allow(dead_code)]
-    pub fn write_autosave_beta(&self, save: &Save) {
+    pub fn write_autosave_beta(&mut self, fs: &impl FileSystem, save: &Save) {
         if let Ok(modified_save) = &save.to_string() {
-            let _ = fs::write(&self.full_path, modified_save);
-            println!("Save succeeded: autosaveBETA");
+            let result = fs.write_atomic(Path::new(&self.full_path), modified_save.as_bytes());
+            self.write_stats.record("autosaveBETA", result);
         }
     }
-    pub fn write_txt_basic_info(&self, save: &Save) {
-        if let Ok(txt_body) = compose_txt_basic_info(save) {
-            let _ = fs::write(self.write_txt_dir_path.clone() + "sts_basic_info.txt", txt_body);
-            println!("Save succeeded: basic-info txt");
+    pub fn write_txt_basic_info(&mut self, fs: &impl FileSystem, save: &Save, progress: Option<&CharacterProgress>) {
+        if let Ok(txt_body) = compose_txt_basic_info(save, progress) {
+            self.write_txt(fs, "sts_basic_info.txt", "basic-info txt", &txt_body);
         }
     }
-    pub fn write_txt_enemies(&self, save: &Save) {
+    pub fn write_txt_progress(&mut self, fs: &impl FileSystem, prefs: &Preferences) {
+        self.write_txt(fs, "sts_progress.txt", "progress txt", &compose_txt_progress(prefs));
+    }
+    // デイリーでなければ空txt
+    pub fn write_txt_daily(&mut self, fs: &impl FileSystem, daily: Option<&DailyRun>) {
+        let txt_body = daily.map(compose_txt_daily).unwrap_or_else(|| compose_txt_empty().unwrap());
+        self.write_txt(fs, "sts_daily.txt", "daily txt", &txt_body);
+    }
+    pub fn write_txt_modifiers(&mut self, fs: &impl FileSystem, tag: &RunTag) {
+        self.write_txt(fs, "sts_modifiers.txt", "modifiers txt", &compose_txt_modifiers(tag));
+    }
+    pub fn write_txt_streak(&mut self, fs: &impl FileSystem, txt_body: &str) {
+        self.write_txt(fs, "sts_streak.txt", "streak txt", txt_body);
+    }
+    pub fn write_txt_enemies(&mut self, fs: &impl FileSystem, save: &Save) {
         if let Ok(txt_body) = compose_txt_enemies(save) {
-            let _ = fs::write(self.write_txt_dir_path.clone() + "sts_enemies.txt", txt_body);
-            println!("Save succeeded: enemies txt");
+            self.write_txt(fs, "sts_enemies.txt", "enemies txt", &txt_body);
+        }
+    }
+    pub fn write_txt_last_change(&mut self, fs: &impl FileSystem, diff: &SaveDiff) {
+        let txt_body = compose_txt_last_change(diff);
+        self.write_txt(fs, "sts_last_change.txt", "last-change txt", &txt_body);
+    }
+    pub fn write_txt_session(&mut self, fs: &impl FileSystem, info: &SessionInfo) {
+        let txt_body = compose_txt_session(info);
+        self.write_txt(fs, "sts_session.txt", "session txt", &txt_body);
+    }
+    // タイマーは毎tick書き換わるので成功ログはtraceに抑える
+    pub fn write_txt_timer(&mut self, fs: &impl FileSystem, timer: &RunTimer, now_secs: u64, game_running: Option<bool>) {
+        let txt_body = compose_txt_timer(timer, now_secs, game_running);
+        let txt_path = self.write_txt_dir_path.clone() + "sts_timer.txt";
+        let result = fs.write_atomic(Path::new(&txt_path), txt_body.as_bytes());
+        self.write_stats.record_quiet("timer txt", result);
+    }
+    // 毎tick更新される出力(セッション・タイマー)を空にする
+    pub fn write_txt_empty_live(&mut self, fs: &impl FileSystem) {
+        if let Ok(txt_body) = compose_txt_empty() {
+            self.write_txt(fs, "sts_session.txt", "EMPTY session txt", &txt_body);
+            self.write_txt(fs, "sts_timer.txt", "EMPTY timer txt", &txt_body);
+        }
+    }
+    pub fn write_txt_seed(&mut self, fs: &impl FileSystem, run_seed: &RunSeed) {
+        let txt_body = compose_txt_seed(run_seed);
+        self.write_txt(fs, "sts_seed.txt", "seed txt", &txt_body);
+    }
+    pub fn write_txt_map(&mut self, fs: &impl FileSystem, save: &Save, map_path: &MapPath) {
+        let txt_body = compose_txt_map(save, map_path);
+        self.write_txt(fs, "sts_map.txt", "map txt", &txt_body);
+    }
+    // 振り返り用のCSV/JSONはrun終了後も残す(write_txt_emptyで消さない)
+    pub fn write_txt_timeline(&mut self, fs: &impl FileSystem, timeline: &RunTimeline, act: u32) {
+        let txt_body = compose_txt_timeline(timeline, act);
+        self.write_txt(fs, "sts_timeline.txt", "timeline txt", &txt_body);
+        self.write_txt(fs, "sts_timeline.csv", "timeline csv", &compose_csv_timeline(timeline));
+        if let Ok(json_body) = serde_json::to_string_pretty(timeline) {
+            self.write_txt(fs, "sts_timeline.json", "timeline json", &(json_body + "\n"));
+        }
+    }
+    pub fn write_txt_events(&mut self, fs: &impl FileSystem, save: &Save) {
+        self.write_txt(fs, "sts_neow.txt", "neow txt", &compose_txt_neow(save));
+        self.write_txt(fs, "sts_events.txt", "events txt", &compose_txt_events(save));
+    }
+    pub fn write_txt_deck_stats(&mut self, fs: &impl FileSystem, stats: &DeckStats) {
+        let txt_body = compose_txt_deck_stats(stats);
+        self.write_txt(fs, "sts_deck_stats.txt", "deck-stats txt", &txt_body);
+    }
+    pub fn write_txt_boss(&mut self, fs: &impl FileSystem, save: &Save) {
+        let txt_body = compose_txt_boss(save);
+        self.write_txt(fs, "sts_boss.txt", "boss txt", &txt_body);
+    }
+    pub fn write_txt_economy(&mut self, fs: &impl FileSystem, economy: &Economy) {
+        let txt_body = compose_txt_economy(economy);
+        self.write_txt(fs, "sts_economy.txt", "economy txt", &txt_body);
+    }
+    pub fn write_txt_card_choices(&mut self, fs: &impl FileSystem, choices: &[ChoiceRecord], shown: usize) {
+        let txt_body = compose_txt_card_choices(choices, shown);
+        self.write_txt(fs, "sts_card_choices.txt", "card-choices txt", &txt_body);
+    }
+    pub fn write_json_card_choices(&mut self, fs: &impl FileSystem, choices_json: &Value) {
+        if let Ok(json_body) = serde_json::to_string_pretty(choices_json) {
+            self.write_txt(fs, "sts_card_choices.json", "card-choices json", &(json_body + "\n"));
         }
     }
-    pub fn write_txt_empty(&self) {
+    pub fn write_txt_pick_rates(&mut self, fs: &impl FileSystem, history: &CardChoiceHistory, character: &str, kind: RunKind) {
+        let txt_body = compose_txt_pick_rates(character, kind, history.runs_of(character, kind),
+                                              &history.pick_rates(character, kind));
+        self.write_txt(fs, "sts_card_pick_rates.txt", "pick-rates txt", &txt_body);
+    }
+    pub fn write_json_run(&mut self, fs: &impl FileSystem, run_json: &Value) {
+        if let Ok(json_body) = serde_json::to_string_pretty(run_json) {
+            self.write_txt(fs, "sts_run.json", "run json", &(json_body + "\n"));
+        }
+    }
+    pub fn write_txt_empty(&mut self, fs: &impl FileSystem) {
         if let Ok(txt_body) = compose_txt_empty() {
-            let _ = fs::write(self.write_txt_dir_path.clone() + "sts_basic_info.txt", &txt_body);
-            let _ = fs::write(self.write_txt_dir_path.clone() + "sts_enemies.txt", &txt_body);
-            println!("Save succeeded: EMPTY txts");
+            self.write_txt(fs, "sts_basic_info.txt", "EMPTY basic-info txt", &txt_body);
+            self.write_txt(fs, "sts_enemies.txt", "EMPTY enemies txt", &txt_body);
+            self.write_txt(fs, "sts_last_change.txt", "EMPTY last-change txt", &txt_body);
+            self.write_txt(fs, "sts_seed.txt", "EMPTY seed txt", &txt_body);
+            self.write_txt(fs, "sts_map.txt", "EMPTY map txt", &txt_body);
+            self.write_txt(fs, "sts_timeline.txt", "EMPTY timeline txt", &txt_body);
+            self.write_txt(fs, "sts_card_choices.txt", "EMPTY card-choices txt", &txt_body);
+            self.write_txt(fs, "sts_neow.txt", "EMPTY neow txt", &txt_body);
+            self.write_txt(fs, "sts_events.txt", "EMPTY events txt", &txt_body);
+            self.write_txt(fs, "sts_economy.txt", "EMPTY economy txt", &txt_body);
+            self.write_txt(fs, "sts_boss.txt", "EMPTY boss txt", &txt_body);
+            self.write_txt(fs, "sts_deck_stats.txt", "EMPTY deck-stats txt", &txt_body);
+            self.write_txt(fs, "sts_daily.txt", "EMPTY daily txt", &txt_body);
+            self.write_txt(fs, "sts_modifiers.txt", "EMPTY modifiers txt", &txt_body);
+        }
+        self.write_txt(fs, "sts_run.json", "EMPTY run json", "{}\n");
+    }
+    // txt出力先ディレクトリへの書き出し(成否はWriteStatsに記録)
+    fn write_txt(&mut self, fs: &impl FileSystem, file_name: &str, label: &str, txt_body: &str) {
+        let txt_path = self.write_txt_dir_path.clone() + file_name;
+        let result = fs.write_atomic(Path::new(&txt_path), txt_body.as_bytes());
+        self.write_stats.record(label, result);
+    }
+}
+
+// 書き出しの成否を数えるカウンタ
+#[derive(Debug, Clone, Default)]
+pub struct WriteStats {
+    written: u64,
+    unchanged: u64,
+    failed: u64,
+}
+#[rustfmt::skip]
+impl WriteStats {
+    pub fn written(&self) -> u64 { self.written }
+    pub fn unchanged(&self) -> u64 { self.unchanged }
+    pub fn failed(&self) -> u64 { self.failed }
+    pub fn merge(&mut self, other: WriteStats) {
+        self.written += other.written;
+        self.unchanged += other.unchanged;
+        self.failed += other.failed;
+    }
+
+    pub fn record(&mut self, label: &str, result: std::io::Result<WriteOutcome>) {
+        match result {
+            Ok(WriteOutcome::Written) => {
+                self.written += 1;
+                info!("Save succeeded: {}", label);
+            },
+            Ok(WriteOutcome::Unchanged) => {
+                self.unchanged += 1;
+                debug!("Save skipped (unchanged): {}", label);
+            },
+            Err(e) => {
+                self.failed += 1;
+                error!("Save failed: {}: {} (failures so far: {})", label, e, self.failed);
+            },
+        }
+    }
+    // 頻繁に書き出すファイル向け(失敗以外はtraceで記録)
+    pub fn record_quiet(&mut self, label: &str, result: std::io::Result<WriteOutcome>) {
+        match result {
+            Ok(WriteOutcome::Written) => {
+                self.written += 1;
+                trace!("Save succeeded: {}", label);
+            },
+            Ok(WriteOutcome::Unchanged) => self.unchanged += 1,
+            Err(_) => self.record(label, result),
         }
     }
 }
 
-const LOCAL_JSON_PATH: &str = "./settings.json";
+// txtの出力先(未指定ならプラットフォームごとの既定値、それも無ければカレントディレクトリ)
+pub fn write_txt_dir_path(json_data: &JsonData) -> String {
+    let dir = json_data.get_path_from_key("write_txt_dir_path")
+                       .or_else(|| default_output_dir(&env_var))
+                       .unwrap_or_else(|| PathBuf::from("."));
+    dir_string(&dir)
+}
 
 // JSONのパラメータを格納する構造体
+// settings.jsonは読み込み専用(実行時の状態はStateDataに保存する)
 #[derive(Debug, Clone)]
 pub struct JsonData {
-    path: &'static Path,
+    path: PathBuf,
     body: Value,
+    file_stamp: Option<FileMeta>, // 変更検知用
 }
 #[rustfmt::skip]
 impl JsonData {
-    pub fn new() -> Self {
-        Self {
-            path: Path::new(LOCAL_JSON_PATH),
-            body: {
-                let json_content = fs::read_to_string(Path::new(LOCAL_JSON_PATH)).unwrap();
-                serde_json::from_str(&json_content).map_err(|e| Error::custom(e.to_string())).unwrap()
-            },
-        }
+    // 設定ファイルを探す順番はsettings_candidates()の通り
+    pub fn new(fs: &impl FileSystem) -> Self {
+        let candidates = settings_candidates(&env_var, exe_dir());
+        let path = find_settings(fs, &candidates).unwrap_or_else(|| {
+            let searched: Vec<String> = candidates.iter().map(|path| path.display().to_string()).collect();
+            panic!("{} not found (searched: {})", SETTINGS_FILE_NAME, searched.join(", "))
+        });
+        let file_stamp = fs.metadata(&path).ok();
+        let body = read_json_body(fs, &path).unwrap_or_else(|e| panic!("Invalid {}: {}", path.display(), e));
+        Self { path, body, file_stamp }
+    }
+    #[cfg(test)]
+    pub fn from_value(body: Value) -> Self {
+        Self { path: PathBuf::from(".").join(SETTINGS_FILE_NAME), body, file_stamp: None }
     }
+    pub fn path(&self) -> &Path { &self.path }
     pub fn get_value_from_key<T>(&self, key: &str) -> Option<T>
         where T: serde::de::DeserializeOwned {
         self.body.get(key).and_then(|value| serde_json::from_value(value.clone()).ok())
     }
-    pub fn update_json_body<T>(&mut self, key: &str, new_value: &T)
-        where T: serde::Serialize + serde::de::DeserializeOwned + 'static, {
-        let current_value = &self.body[key];
-        let new_value_type_id = std::any::TypeId::of::<T>();
+    // パスの設定値(相対パスは設定ファイルのあるディレクトリから解決する)
+    pub fn get_path_from_key(&self, key: &str) -> Option<PathBuf> {
+        self.get_value_from_key(key).map(|path| self.resolve_path(path))
+    }
+    // 相対パスを設定ファイルのあるディレクトリから解決する(カレントディレクトリの設定ファイルならそのまま)
+    pub fn resolve_path(&self, path: PathBuf) -> PathBuf {
+        match self.path.parent() {
+            Some(dir) if path.is_relative() && !dir.as_os_str().is_empty() && dir != Path::new(".") => dir.join(path),
+            _ => path,
+        }
+    }
 
-        // JSON上書き前に型チェック
-        match (current_value, new_value_type_id) {
-            (Value::Number(_), tid) if tid == std::any::TypeId::of::<u64>() => {
-                self.body[key] = json!(new_value);
-            },
-            (Value::String(_), tid) if tid == std::any::TypeId::of::<String>()
-                || tid == std::any::TypeId::of::<&str>()
-                || tid == std::any::TypeId::of::<&String>()
-                || tid == std::any::TypeId::of::<std::string::String>() => {
-                self.body[key] = json!(new_value);
-            },
-            (Value::Bool(_), tid) if tid == std::any::TypeId::of::<bool>() => {
-                self.body[key] = json!(new_value);
-            },
-            _ => {
-                panic!("Incompatible types ('{}') for key: {}", type_of(&new_value), key);
-            }
+    /* settings.jsonが変更されていれば再読み込みする
+
+      変更なし: Ok(false)
+      変更あり(有効な内容): 新しい内容に差し替えてOk(true)
+      変更あり(無効な内容): 以前の内容を保持したままErr
+    */
+    pub fn reload_if_modified(&mut self, fs: &impl FileSystem) -> Result<bool, String> {
+        let file_stamp = fs.metadata(&self.path).ok();
+        if file_stamp == self.file_stamp {
+            return Ok(false);
         }
-        let _ = fs::write(self.path, self.body.to_string());
+        // 無効な内容でも同じ編集内容に対してエラーを繰り返さないよう記録だけは更新
+        self.file_stamp = file_stamp;
+        self.body = read_json_body(fs, &self.path)?;
+        Ok(true)
     }
-    // トレイト境界AsRef<Path>により、Path型もPathBuf型も両方受け取れる
-    pub fn compare_modified_time<P: AsRef<Path>>(&self, autosave_path: &P) -> ModifiedTimeStatus {
-        let autosave_modified_time = get_file_modified_time(autosave_path).unwrap();
-        let json_autosave_modified_time: u64 = self.get_value_from_key("modified_time").unwrap();
+}
+
+// 候補のうち最初に見つかった設定ファイル
+fn find_settings(fs: &impl FileSystem, candidates: &[PathBuf]) -> Option<PathBuf> {
+    candidates.iter().find(|path| fs.is_file(path)).cloned()
+}
+
+// settings.jsonを読み込み、必須キーの型と値を検証する
+fn read_json_body(fs: &impl FileSystem, path: &Path) -> Result<Value, String> {
+    let json_content = fs.read_to_string(path).map_err(|e| e.to_string())?;
+    let body: Value = serde_json::from_str(&json_content).map_err(|e| e.to_string())?;
 
-        match autosave_modified_time.cmp(&json_autosave_modified_time) {
-            std::cmp::Ordering::Less => ModifiedTimeStatus::Old,
-            std::cmp::Ordering::Greater => ModifiedTimeStatus::New,
-            std::cmp::Ordering::Equal => ModifiedTimeStatus::Equal,
+    let check = |key: &str, required: bool, is_valid: fn(&Value) -> bool, expected: &str| -> Result<(), String> {
+        match body.get(key) {
+            Some(value) if is_valid(value) => Ok(()),
+            Some(value) => Err(format!("'{}' must be {} (found: {})", key, expected, value)),
+            None if required => Err(format!("'{}' is missing", key)),
+            None => Ok(()),
         }
+    };
+    let is_positive = |v: &Value| v.as_u64().is_some_and(|n| n > 0);
+    check("autosave_dir_path", true, |v| v.is_string(), "a string")?;
+    check("write_txt_dir_path", false, |v| v.is_string(), "a string")?;
+    check("max_mode_repeat", true, |v| v.as_u64().is_some_and(|n| n <= u16::MAX as u64), "an integer (0-65535)")?;
+    check("loop_interval_ms", false, is_positive, "a positive integer")?;
+    check("waiting_interval_ms", false, is_positive, "a positive integer")?;
+    check("watching_interval_ms", false, is_positive, "a positive integer")?;
+    check("active_interval_ms", false, is_positive, "a positive integer")?;
+    check("max_waiting_interval_ms", false, is_positive, "a positive integer")?;
+    check("recent_change_secs", false, |v| v.is_u64(), "a non-negative integer")?;
+    check("detect_game_process", false, |v| v.is_boolean(), "a boolean")?;
+    check("card_choices_shown", false, is_positive, "a positive integer")?;
+    check("preferences_dir_path", false, |v| v.is_string(), "a string")?;
+    check("runs_dir_path", false, |v| v.is_string(), "a string")?;
+    check("streak_mode", false, |v| v.as_str().is_some_and(|mode| StreakMode::parse(mode).is_some()),
+          "\"per_character\", \"rotating\" or \"any\"")?;
+    check("streak_min_ascension", false, |v| v.as_u64().is_some_and(|n| n <= 20), "an integer (0-20)")?;
+    check("streak_rotation", false, |v| v.as_array().is_some_and(|list| !list.is_empty() && list.iter().all(Value::is_string)),
+          "a non-empty array of strings")?;
+    // ログ設定もここで検証しておき、logger::configureで失敗して設定の一部だけ反映されることがないようにする
+    check("log_level", false, |v| v.as_str().is_some_and(|name| Level::parse(name).is_some()),
+          "\"error\", \"warn\", \"info\", \"debug\" or \"trace\"")?;
+    check("log_format", false, |v| matches!(v.as_str(), Some("text" | "json")), "\"text\" or \"json\"")?;
+    check("log_file_path", false, |v| v.is_string(), "a string")?;
+    check("log_file_max_bytes", false, is_positive, "a positive integer")?;
+    check("log_file_max_files", false, |v| v.as_u64().is_some_and(|n| n <= u32::MAX as u64), "a non-negative integer")?;
+    Ok(body)
+}
+
+// ゲームのディレクトリ(preferences, runsなど)のパス
+// 設定で指定されていなければautosaveディレクトリと同じ階層にあるものとみなす
+pub fn game_dir_path(json_data: &JsonData, key: &str, dir_name: &str) -> PathBuf {
+    if let Some(path) = json_data.get_path_from_key(key) {
+        return path;
     }
+    let autosave_dir_path = json_data.get_path_from_key("autosave_dir_path").unwrap_or_default();
+    autosave_dir_path.parent().unwrap_or(Path::new("")).join(dir_name)
 }
 
-#[allow(dead_code)]
-fn type_of<T>(_: &T) -> &'static str { std::any::type_name::<T>() }
+// 数に合わせた名詞の形(1なら単数形、それ以外は複数形) 例: "attack"/"attacks", "status"/"statuses"
+pub fn plural(count: u32, noun: &str) -> String {
+    match (count, noun.ends_with('s')) {
+        (1, _) => noun.to_string(),
+        (_, true) => format!("{}es", noun),
+        (_, false) => format!("{}s", noun),
+    }
+}
 
 // Path型やOsStr型向けに共通化されたto_string()関数
 fn to_string<T: AsRef<OsStr>>(value: T) -> String {
@@ -165,17 +417,88 @@ fn to_string<T: AsRef<OsStr>>(value: T) -> String {
 // XXX: こっちだとOsStr型がToStringトレイトを持ってないからエラー
 // fn to_string<T: ToString>(value: T) -> String { value.to_string() }
 
-pub fn wait_ms(time_ms: u64) { std::thread::sleep(std::time::Duration::from_millis(time_ms)) }
+// autosaveファイルを読み込んでSaveにデコードする
+pub fn load_save<P: AsRef<Path>>(fs: &impl FileSystem, path: P) -> Result<Save, String> {
+    load_save_with_raw(fs, path).map(|(save, _)| save)
+}
+// libstsのSaveにないフィールド(seed_setなど)用に、復号した生のJSONも一緒に返す
+pub fn load_save_with_raw<P: AsRef<Path>>(fs: &impl FileSystem, path: P) -> Result<(Save, Value), String> {
+    let path = path.as_ref();
+    let contents = fs.read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let raw = decode_save_json(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let save = Save::deserialize(&raw).map_err(|e| format!("{}: JSON error: {}", path.display(), e))?;
+    Ok((save, raw))
+}
+
+// libstsと同じ手順で復号する(base64+"key"とのXOR、失敗すれば平文のJSONとみなす)
+fn decode_save_json(contents: &str) -> Result<Value, Error> {
+    match decode_base64(contents.trim()) {
+        Some(bytes) => {
+            let key = b"key";
+            let decoded: Vec<u8> = bytes.iter().enumerate().map(|(i, b)| b ^ key[i % key.len()]).collect();
+            serde_json::from_slice(&decoded)
+        },
+        None => serde_json::from_str(contents),
+    }
+}
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let text = text.trim_end_matches('=').as_bytes();
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0_u32, 0_u32);
+    for &c in text {
+        buffer = (buffer << 6) | sextet(c)? as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
 
-fn compose_txt_basic_info(save: &Save) -> Result<String, Error> {
+// 設定ファイルからキャラクターの進行状況が読めればAscension levelの行に添える
+fn compose_txt_basic_info(save: &Save, progress: Option<&CharacterProgress>) -> Result<String, Error> {
     let mut ret: String = String::new();
     ret.push_str(&format!("Player's Name: {}\n", save.name));
-    ret.push_str(&format!("Ascension level: {}\n", save.ascension_level.to_string().as_str()));
+    let suffix = progress.map(compose_ascension_suffix).unwrap_or_default();
+    ret.push_str(&format!("Ascension level: {}{}\n", save.ascension_level.to_string().as_str(), suffix));
+    // 通常のrunでなければモディファイアを添える
+    ret.push_str(&compose_run_type_line(&RunTag::new(save)));
     ret.push_str(&format!("Gold: {}\n", save.gold.to_string().as_str()));
     ret.push_str(&format!("Health: {}/{}\n", save.current_health.to_string().as_str(), save.max_health.to_string().as_str()));
-    ret.push_str(&format!("Play time: {}\n", save.play_time.to_string().as_str()));
+    ret.push_str(&format!("Play time: {}\n", format_hms(save.play_time as u64)));
     Ok(ret)
 }
+// sts_run.jsonの内容(各出力の情報をまとめたもの)
+pub fn compose_json_run(save: &Save, run_seed: &RunSeed, map_path: &MapPath) -> Value {
+    serde_json::json!({
+        "name": save.name,
+        "ascension_level": save.ascension_level,
+        "act": save.act_num,
+        "floor": save.floor_num as i64,
+        "gold": save.gold,
+        "current_health": save.current_health,
+        "max_health": save.max_health,
+        "play_time": save.play_time,
+        "seed": run_seed.to_json(),
+        "map": map_path.to_json(),
+        "neow": compose_json_neow(save),
+        "events": compose_json_events(save),
+        "economy": Economy::new(save).to_json(),
+        "boss": compose_json_boss(save),
+        "deck_stats": DeckStats::new(save),
+        "daily": DailyRun::new(save).map(|daily| daily.to_json()),
+        "modifiers": RunTag::new(save).to_json(),
+    })
+}
 fn compose_txt_enemies(save: &Save) -> Result<String, Error> {
     let mut ret: String = String::new();
     for (n, monster) in save.monster_list.iter().enumerate() {
@@ -199,40 +522,72 @@ fn compose_txt_empty() -> Result<String, Error> {
 // Pathからファイル名(basename)を取得してString型で返す
 fn get_file_basename(path: &Path) -> String { to_string(path.file_stem().unwrap()) }
 
+// キャラクター(autosaveのファイル名)
+pub const CHARACTERS: [&str; 4] = ["IRONCLAD", "THE_SILENT", "DEFECT", "WATCHER"];
+// キャラクターごとの初期レリック(ボスレリックで置き換えた後のものも含む)と基本カードの接尾辞
+const CHARACTER_MARKERS: [(&str, [&str; 2], &str); 4] = [
+    ("IRONCLAD", ["Burning Blood", "Black Blood"], "_R"),
+    ("THE_SILENT", ["Ring of the Snake", "Ring of the Serpent"], "_G"),
+    ("DEFECT", ["Cracked Core", "FrozenCore"], "_B"),
+    ("WATCHER", ["PureWater", "HolyWater"], "_P"),
+];
+
+/* autosaveのキャラクター(集計や設定ファイルのキーに使う)
+
+  ファイル名がキャラクター名ならそれを使う("1_IRONCLAD"のようなセーブスロットの接頭辞は除く)
+  デイリーなどファイル名から分からなければ、初期レリックか基本カード(Strike_R, Defend_Gなど)から判断する
+*/
+pub fn character_of(file_stem: &str, save: &Save) -> String {
+    let name = match file_stem.split_once('_') {
+        Some((slot, name)) if !slot.is_empty() && slot.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => file_stem,
+    };
+    if CHARACTERS.contains(&name) {
+        return name.to_string();
+    }
+    let by_relic = CHARACTER_MARKERS.iter().find(|(_, relics, _)| {
+        save.relics.iter().any(|relic| relics.contains(&relic.as_str()))
+    });
+    let by_cards = || CHARACTER_MARKERS.iter().find(|(_, _, suffix)| {
+        save.cards.iter().any(|card| {
+            (card.id.starts_with("Strike") || card.id.starts_with("Defend")) && card.id.ends_with(suffix)
+        })
+    });
+    match by_relic.or_else(by_cards) {
+        Some((character, _, _)) => character.to_string(),
+        None => name.to_string(),
+    }
+}
+
 // Path/PathBufからファイルの更新日時を取得する
-fn get_file_modified_time<P: AsRef<Path>>(path: &P) -> Result<u64, Error> {
-    let target_file = fs::metadata(path).expect("metadata() failed");
-    Ok(target_file.modified()
-                  .expect("modified_time: modified() failed")
-                  .duration_since(std::time::SystemTime::UNIX_EPOCH)
-                  .expect("modified_time: duration_since() failed")
-                  .as_secs())
+pub fn get_file_modified_time<P: AsRef<Path>>(fs: &impl FileSystem, path: &P) -> std::io::Result<u64> {
+    Ok(fs.metadata(path.as_ref())?.modified_secs())
 }
 
 #[rustfmt::skip]
 pub fn autosave_mode_selector(
+        fs: &impl FileSystem,
         mode_selector: &mut ModeSelector,
         target: &mut Target,
-        json_data: &mut JsonData,
+        state_data: &mut StateData,
         autosave_path: &Path
     ) {
-    match json_data.compare_modified_time(&autosave_path) {
+    match state_data.compare_modified_time(fs, &autosave_path) {
         ModifiedTimeStatus::New => {
-            println!("The found autosave is NEWer than JSON's one!");
+            debug!("The found autosave is NEWer than the last observed one!");
             // 監視対象のフルパス・キャラクタータイプ・更新日時を更新
-            target.update_params(&autosave_path);
-            // JSONの値を監視対象の値で上書き
-            json_data.update_json_body("modified_time", &target.modified_time());
-            json_data.update_json_body("character_type", &target.character_type());
+            target.update_params(fs, &autosave_path);
+            // 状態ファイルの値を監視対象の値で上書き
+            state_data.update(fs, target);
             // 監視対象を発見したのでモードセレクト変数を更新
             mode_selector.found_target();
             // 更新差分があるのでファイル書き出しON
             mode_selector.turn_on_do_writing();
         },
         ModifiedTimeStatus::Equal => {
-            println!("The found autosave is SAME as JSON's one!");
+            trace!("The found autosave is SAME as the last observed one!");
             // 監視対象のフルパス・キャラクタータイプ・更新日時を更新
-            target.update_params(&autosave_path);
+            target.update_params(fs, &autosave_path);
             // 監視対象を発見したのでモードセレクト変数を更新
             mode_selector.found_target();
             // 更新差分がないのでファイル書き出しOFF
@@ -244,26 +599,87 @@ pub fn autosave_mode_selector(
     }
 }
 
-pub fn switch_to_fileio<T>(mode_selector: &mut ModeSelector, fileio_mode: Mode, mode: &T)
-    where T: std::fmt::Debug + Clone + PartialEq {
+pub fn switch_to_fileio(mode_selector: &mut ModeSelector, event: Event) {
     if mode_selector.do_writing() {
         // 書き出しスイッチがONならファイルI/Oモードに遷移
-        println!("Mode transition: from {:?} to FileIO", mode);
         mode_selector.reset_times_repeated();
-        mode_selector.switch_mode(&fileio_mode);
-        assert_eq!(mode_selector.current_mode(), fileio_mode);
+        fire_event(mode_selector, event);
     } else {
         // 新しいautosaveが見つかるまで現在のモードを反復
-        println!("Now on interval...(Mode: {:?})\n", mode);
+        trace!("Now on interval...(Mode: {})", mode_selector.current_mode());
         mode_selector.increase_times_repeated();
     }
 }
 
-pub fn switch_to_waiting(mode_selector: &mut ModeSelector, waiting_mode: Mode) {
+pub fn switch_to_waiting(mode_selector: &mut ModeSelector, event: Event) {
     mode_selector.reset_target();
     mode_selector.reset_times_repeated();
     mode_selector.turn_on_do_writing(); // 待機モードで最初の空txt出力をON
-    println!("Mode transition: from Watching to Waiting");
-    mode_selector.switch_mode(&waiting_mode);
-    assert_eq!(mode_selector.current_mode(), waiting_mode);
+    fire_event(mode_selector, event);
+}
+
+// 遷移表にない遷移はパニックせずにエラーとして記録し、現在のモードを維持する
+pub fn fire_event(mode_selector: &mut ModeSelector, event: Event) {
+    if let Err(e) = mode_selector.fire(event) {
+        error!("{}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture::*;
+
+    #[test]
+    fn decodes_obfuscated_and_plain_saves() {
+        assert_eq!(decode_base64("aGVsbG8=").as_deref(), Some(&b"hello"[..]));
+        let encoded = example_save().to_b64_string().unwrap();
+        assert_eq!(decode_save_json(&encoded).unwrap()["seed"], 1234567890123_i64);
+        assert_eq!(decode_save_json(AUTOSAVE).unwrap()["seed_set"], false);
+    }
+
+    #[test]
+    fn relative_paths_resolve_against_settings_dir() {
+        let mut json_data = JsonData::from_value(serde_json::json!({ "data_dir_path": "./data/", "log_file_path": "/var/log/sts.log" }));
+        assert_eq!(json_data.get_path_from_key("data_dir_path"), Some(PathBuf::from("./data/")));
+        json_data.path = PathBuf::from("/home/me/.config/sts-observer/settings.json");
+        assert_eq!(json_data.get_path_from_key("data_dir_path"), Some(PathBuf::from("/home/me/.config/sts-observer/./data/")));
+        assert_eq!(json_data.get_path_from_key("log_file_path"), Some(PathBuf::from("/var/log/sts.log")));
+    }
+
+    #[test]
+    fn invalid_log_settings_reject_the_whole_edit() {
+        let fs = crate::system::fake::MemFileSystem::default();
+        let mut json_data = JsonData::from_value(serde_json::json!({}));
+        fs.set_file(json_data.path(), r#"{"autosave_dir_path": "/saves/", "max_mode_repeat": 3}"#, 1);
+        assert_eq!(json_data.reload_if_modified(&fs), Ok(true));
+
+        fs.set_file(json_data.path(), r#"{"autosave_dir_path": "/other/", "max_mode_repeat": 3, "log_level": "verbose"}"#, 2);
+        assert!(json_data.reload_if_modified(&fs).unwrap_err().contains("'log_level'"));
+        assert_eq!(json_data.get_value_from_key::<String>("autosave_dir_path").as_deref(), Some("/saves/"));
+
+        fs.set_file(json_data.path(), r#"{"autosave_dir_path": "/other/", "max_mode_repeat": 3, "log_format": "xml"}"#, 3);
+        assert!(json_data.reload_if_modified(&fs).unwrap_err().contains("'log_format'"));
+    }
+
+    #[test]
+    fn first_existing_settings_file_wins() {
+        let fs = crate::system::fake::MemFileSystem::default();
+        let candidates = [PathBuf::from("./settings.json"), PathBuf::from("/cfg/settings.json"), PathBuf::from("/exe/settings.json")];
+        assert_eq!(find_settings(&fs, &candidates), None);
+        fs.set_file("/exe/settings.json", "{}", 0);
+        fs.set_file("/cfg/settings.json", "{}", 0);
+        assert_eq!(find_settings(&fs, &candidates), Some(PathBuf::from("/cfg/settings.json")));
+    }
+
+    #[test]
+    fn character_comes_from_slot_files_and_save_contents() {
+        let mut save = example_save();
+        assert_eq!(character_of("1_THE_SILENT", &save), "THE_SILENT");
+        assert_eq!(character_of("DAILY", &save), "IRONCLAD");
+        // 初期レリックを交換していても基本カードから分かる
+        save.relics = vec![String::from("Runic Dome")];
+        save.cards.iter_mut().for_each(|card| card.id = card.id.replace("_R", "_P"));
+        assert_eq!(character_of("DAILY", &save), "WATCHER");
+    }
 }
@@ -0,0 +1,71 @@
+// デイリー挑戦(Daily Climb)のrunを扱うモジュール
+//
+// デイリーのautosaveにはis_dailyが立ち、日付(daily_date: 1970-01-01からの日数)とモディファイアが入る
+use libsts::Save;
+use serde_json::{json, Value};
+
+use crate::logger::civil_from_days;
+use crate::modifiers::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyRun {
+    pub date: String, // YYYY-MM-DD
+    pub mods: Vec<String>,
+}
+impl DailyRun {
+    // デイリーでなければNone
+    pub fn new(save: &Save) -> Option<Self> {
+        if !save.is_daily {
+            return None;
+        }
+        Some(Self { date: format_daily_date(save.daily_date), mods: save.daily_mods.clone() })
+    }
+    pub fn to_json(&self) -> Value {
+        json!({ "date": self.date, "mods": self.mods })
+    }
+}
+
+// 1970-01-01からの日数を"YYYY-MM-DD"にする
+pub fn format_daily_date(days: u32) -> String {
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/* sts_daily.txtの内容(デイリーでなければ空の"---")
+
+  Daily Climb: 2024-03-05
+  Modifiers: Binary, Flight
+*/
+pub fn compose_txt_daily(daily: &DailyRun) -> String {
+    let mods = match daily.mods.is_empty() {
+        true => String::from("none"),
+        false => daily.mods.iter().map(|id| mod_name(id)).collect::<Vec<_>>().join(", "),
+    };
+    format!("Daily Climb: {}\nModifiers: {}\n", daily.date, mods)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture::*;
+
+    #[test]
+    fn formats_days_since_epoch() {
+        assert_eq!(format_daily_date(0), "1970-01-01");
+        assert_eq!(format_daily_date(11016), "2000-02-29");
+        assert_eq!(format_daily_date(19787), "2024-03-05");
+    }
+
+    #[test]
+    fn only_daily_saves_have_daily_info() {
+        let mut save = example_save();
+        assert_eq!(DailyRun::new(&save), None);
+
+        save.is_daily = true;
+        save.daily_date = 19787;
+        save.daily_mods = vec![String::from("Binary"), String::from("Flight")];
+        let daily = DailyRun::new(&save).unwrap();
+        assert_eq!(compose_txt_daily(&daily), "Daily Climb: 2024-03-05\nModifiers: Binary, Flight\n");
+        assert_eq!(daily.to_json()["mods"][1], "Flight");
+    }
+}
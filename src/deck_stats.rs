@@ -0,0 +1,124 @@
+// デッキの統計(種類ごとの枚数・コストカーブ・アップグレード率など)を扱うモジュール
+use libsts::Save;
+use serde::Serialize;
+
+use crate::card_db::*;
+use crate::common::plural;
+
+// コストカーブの区分(0, 1, 2, 3+)
+const CURVE_BUCKETS: usize = 4;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DeckStats {
+    pub total: u32,
+    pub attacks: u32,
+    pub skills: u32,
+    pub powers: u32,
+    pub curses: u32,
+    pub statuses: u32,
+    pub unknown: u32,                 // カード表にないカード(MODなど)
+    pub curve: [u32; CURVE_BUCKETS],  // コスト0, 1, 2, 3以上の枚数
+    pub x_cost: u32,
+    pub unplayable: u32,
+    pub upgraded: u32,
+    pub removals: u32,
+    pub average_cost: Option<f64>,    // コストが決まっているカードの平均
+}
+impl DeckStats {
+    pub fn new(save: &Save) -> Self {
+        let mut stats = Self { removals: save.metric_items_purged.len() as u32, ..Self::default() };
+        let mut cost_sum = 0_u32;
+        let mut costed = 0_u32;
+        for card in &save.cards {
+            stats.total += 1;
+            if card.upgrades > 0 {
+                stats.upgraded += 1;
+            }
+            let Some((card_type, cost)) = card_info(&card.id, card.upgrades) else {
+                stats.unknown += 1;
+                continue;
+            };
+            match card_type {
+                CardType::Attack => stats.attacks += 1,
+                CardType::Skill => stats.skills += 1,
+                CardType::Power => stats.powers += 1,
+                CardType::Curse => stats.curses += 1,
+                CardType::Status => stats.statuses += 1,
+            }
+            match cost {
+                X_COST => stats.x_cost += 1,
+                UNPLAYABLE => stats.unplayable += 1,
+                cost => {
+                    stats.curve[(cost as usize).min(CURVE_BUCKETS - 1)] += 1;
+                    cost_sum += cost as u32;
+                    costed += 1;
+                },
+            }
+        }
+        stats.average_cost = (costed > 0).then(|| cost_sum as f64 / costed as f64);
+        stats
+    }
+    pub fn upgrade_percent(&self) -> u32 { self.upgraded * 100 / self.total.max(1) }
+}
+
+/* sts_deck_stats.txtの内容
+
+  Deck: 10 cards (6 attacks, 4 skills, 0 powers, 0 curses, 0 statuses)    <- 1枚なら"1 attack"のように単数形
+  Curve: 0: 0 | 1: 9 | 2: 1 | 3+: 0 | X: 0
+  Upgraded: 0/10 (0%)
+  Average cost: 1.10
+  Removals: 0
+*/
+pub fn compose_txt_deck_stats(stats: &DeckStats) -> String {
+    let counts = [(stats.attacks, "attack"), (stats.skills, "skill"), (stats.powers, "power"),
+                  (stats.curses, "curse"), (stats.statuses, "status")];
+    let types: Vec<String> = counts.iter().map(|&(count, noun)| format!("{} {}", count, plural(count, noun))).collect();
+    let mut ret = format!("Deck: {} {} ({}", stats.total, plural(stats.total, "card"), types.join(", "));
+    if stats.unknown > 0 {
+        ret.push_str(&format!(", {} unknown", stats.unknown));
+    }
+    ret.push_str(")\n");
+    ret.push_str(&format!("Curve: 0: {} | 1: {} | 2: {} | 3+: {} | X: {}\n",
+                          stats.curve[0], stats.curve[1], stats.curve[2], stats.curve[3], stats.x_cost));
+    ret.push_str(&format!("Upgraded: {}/{} ({}%)\n", stats.upgraded, stats.total, stats.upgrade_percent()));
+    match stats.average_cost {
+        Some(average) => ret.push_str(&format!("Average cost: {:.2}\n", average)),
+        None => ret.push_str("Average cost: -\n"),
+    }
+    ret.push_str(&format!("Removals: {}\n", stats.removals));
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture::*;
+    use libsts::Card;
+
+    #[test]
+    fn counts_starter_deck() {
+        let save = example_save();
+        let stats = DeckStats::new(&save);
+        assert_eq!(stats.total, 10);
+        assert_eq!(compose_txt_deck_stats(&stats),
+                   "Deck: 10 cards (6 attacks, 4 skills, 0 powers, 0 curses, 0 statuses)\n\
+                    Curve: 0: 0 | 1: 9 | 2: 1 | 3+: 0 | X: 0\n\
+                    Upgraded: 0/10 (0%)\n\
+                    Average cost: 1.10\n\
+                    Removals: 0\n");
+    }
+
+    #[test]
+    fn uses_upgraded_cost_and_separates_uncosted_cards() {
+        let mut save = example_save();
+        let card = |id: &str, upgrades: u32| Card { id: id.to_string(), misc: 0, upgrades };
+        save.cards = vec![card("Barricade", 1), card("Whirlwind", 0), card("AscendersBane", 0), card("Modded Card", 0)];
+        save.metric_items_purged = vec!["Strike_R".to_string()];
+        let stats = DeckStats::new(&save);
+        assert_eq!(stats.curve, [0, 0, 1, 0]);
+        assert_eq!((stats.x_cost, stats.unplayable, stats.unknown, stats.removals), (1, 1, 1, 1));
+        assert_eq!(stats.average_cost, Some(2.0));
+        assert_eq!(stats.upgrade_percent(), 25);
+        assert!(compose_txt_deck_stats(&stats).starts_with("Deck: 4 cards (1 attack, 0 skills, 1 power, 1 curse, 0 statuses, 1 unknown)\n"));
+    }
+}
@@ -0,0 +1,206 @@
+// 2つのSave(autosave)間の差分を扱うモジュール
+use libsts::{Card, Save};
+use std::collections::BTreeMap;
+
+// 空のポーションスロットを表すID
+const EMPTY_POTION_SLOT: &str = "Potion Slot";
+
+// 2つのSave間のフィールド単位の差分を格納する構造体
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SaveDiff {
+    pub cards_added: Vec<String>,
+    pub cards_removed: Vec<String>,
+    pub cards_upgraded: Vec<String>,
+    pub relics_gained: Vec<String>,
+    pub relics_lost: Vec<String>,
+    pub potions_used: Vec<String>,
+    pub potions_gained: Vec<String>,
+    pub gold_delta: i64,
+    pub health_delta: i64,
+    pub max_health_delta: i64,
+    pub floors_advanced: i64,
+}
+impl SaveDiff {
+    pub fn new(old: &Save, new: &Save) -> Self {
+        let (cards_added, cards_removed, cards_upgraded) = diff_cards(&old.cards, &new.cards);
+        Self {
+            cards_added,
+            cards_removed,
+            cards_upgraded,
+            relics_gained: subtract(&new.relics, &old.relics),
+            relics_lost: subtract(&old.relics, &new.relics),
+            potions_used: subtract(&non_empty_potions(old), &non_empty_potions(new)),
+            potions_gained: subtract(&non_empty_potions(new), &non_empty_potions(old)),
+            gold_delta: new.gold as i64 - old.gold as i64,
+            health_delta: new.current_health as i64 - old.current_health as i64,
+            max_health_delta: new.max_health as i64 - old.max_health as i64,
+            floors_advanced: new.floor_num as i64 - old.floor_num as i64,
+        }
+    }
+    pub fn is_empty(&self) -> bool { self == &Self::default() }
+
+    // オーバーレイ向けの短い表記("+1 Inflame"や"-45 gold"など)の一覧
+    pub fn summary_items(&self) -> Vec<String> {
+        let mut ret: Vec<String> = Vec::new();
+        if self.floors_advanced != 0 {
+            ret.push(format!("{:+} floor", self.floors_advanced));
+        }
+        for (card, count) in count_items(&self.cards_added) {
+            ret.push(format!("+{} {}", count, card));
+        }
+        for (card, count) in count_items(&self.cards_removed) {
+            ret.push(format!("-{} {}", count, card));
+        }
+        for card in &self.cards_upgraded {
+            ret.push(format!("upgraded {}", card));
+        }
+        for relic in &self.relics_gained {
+            ret.push(format!("+{}", relic));
+        }
+        for relic in &self.relics_lost {
+            ret.push(format!("-{}", relic));
+        }
+        for potion in &self.potions_used {
+            ret.push(format!("used {}", potion));
+        }
+        for potion in &self.potions_gained {
+            ret.push(format!("+{}", potion));
+        }
+        if self.gold_delta != 0 {
+            ret.push(format!("{:+} gold", self.gold_delta));
+        }
+        if self.health_delta != 0 {
+            ret.push(format!("{:+} HP", self.health_delta));
+        }
+        if self.max_health_delta != 0 {
+            ret.push(format!("{:+} Max HP", self.max_health_delta));
+        }
+        ret
+    }
+}
+
+// カード名の表示(アップグレード済みなら"+"を付与)
+pub fn card_name(card: &Card) -> String {
+    match card.upgrades {
+        0 => card.id.clone(),
+        1 => format!("{}+", card.id),
+        n => format!("{}+{}", card.id, n),
+    }
+}
+
+// デッキの差分を(追加, 削除, アップグレード)に振り分ける
+fn diff_cards(old: &[Card], new: &[Card]) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let mut added: Vec<&Card> = subtract_cards(new, old);
+    let mut removed: Vec<&Card> = subtract_cards(old, new);
+    let mut upgraded: Vec<String> = Vec::new();
+
+    // 同じIDでアップグレード回数だけ増えたカードは追加/削除ではなくアップグレード扱い
+    removed.retain(|old_card| {
+        let found = added.iter().position(|new_card| {
+            new_card.id == old_card.id && new_card.upgrades > old_card.upgrades
+        });
+        match found {
+            Some(idx) => {
+                upgraded.push(card_name(added.remove(idx)));
+                false
+            },
+            None => true,
+        }
+    });
+    (added.into_iter().map(card_name).collect(),
+     removed.into_iter().map(card_name).collect(),
+     upgraded)
+}
+
+// 多重集合としての差(lhs - rhs)
+fn subtract_cards<'a>(lhs: &'a [Card], rhs: &[Card]) -> Vec<&'a Card> {
+    let mut rest: Vec<&Card> = rhs.iter().collect();
+    lhs.iter()
+       .filter(|card| match rest.iter().position(|other| other.id == card.id && other.upgrades == card.upgrades) {
+           Some(idx) => { rest.remove(idx); false },
+           None => true,
+       })
+       .collect()
+}
+fn subtract(lhs: &[String], rhs: &[String]) -> Vec<String> {
+    let mut rest: Vec<&String> = rhs.iter().collect();
+    lhs.iter()
+       .filter(|item| match rest.iter().position(|other| other == item) {
+           Some(idx) => { rest.remove(idx); false },
+           None => true,
+       })
+       .cloned()
+       .collect()
+}
+
+fn non_empty_potions(save: &Save) -> Vec<String> {
+    save.potions.iter().filter(|p| p.as_str() != EMPTY_POTION_SLOT).cloned().collect()
+}
+
+fn count_items(items: &[String]) -> BTreeMap<&str, usize> {
+    let mut ret: BTreeMap<&str, usize> = BTreeMap::new();
+    for item in items {
+        *ret.entry(item.as_str()).or_insert(0) += 1;
+    }
+    ret
+}
+
+// diffサブコマンド向けの詳細表示
+pub fn compose_txt_diff_report(diff: &SaveDiff) -> String {
+    let mut ret: String = String::new();
+    ret.push_str(&format!("Floors advanced: {:+}\n", diff.floors_advanced));
+    ret.push_str(&format!("Gold: {:+}\n", diff.gold_delta));
+    ret.push_str(&format!("Health: {:+}\n", diff.health_delta));
+    ret.push_str(&format!("Max health: {:+}\n", diff.max_health_delta));
+    ret.push_str(&format!("Cards added: {}\n", diff.cards_added.join(", ")));
+    ret.push_str(&format!("Cards removed: {}\n", diff.cards_removed.join(", ")));
+    ret.push_str(&format!("Cards upgraded: {}\n", diff.cards_upgraded.join(", ")));
+    ret.push_str(&format!("Relics gained: {}\n", diff.relics_gained.join(", ")));
+    ret.push_str(&format!("Relics lost: {}\n", diff.relics_lost.join(", ")));
+    ret.push_str(&format!("Potions used: {}\n", diff.potions_used.join(", ")));
+    ret.push_str(&format!("Potions gained: {}\n", diff.potions_gained.join(", ")));
+    ret
+}
+
+// 最後の変更内容を1行にまとめる
+pub fn compose_txt_last_change(diff: &SaveDiff) -> String {
+    if diff.is_empty() {
+        return String::from("No change\n");
+    }
+    let mut ret: String = diff.summary_items().join(", ");
+    ret.push('\n');
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture::*;
+
+    #[test]
+    fn reports_card_relic_gold_and_health_deltas() {
+        let old = example_save();
+        let mut new = example_save();
+        new.cards.push(Card { id: String::from("Inflame"), misc: 0, upgrades: 0 });
+        new.cards.push(Card { id: String::from("Inflame"), misc: 0, upgrades: 0 });
+        let bash = new.cards.iter_mut().find(|card| card.id == "Bash").unwrap();
+        bash.upgrades = 1;
+        let strike = new.cards.iter().position(|card| card.id == "Strike_R").unwrap();
+        new.cards.remove(strike);
+        new.relics.push(String::from("Vajra"));
+        new.gold = old.gold + 50;
+        new.current_health = old.current_health - 7;
+
+        let diff = SaveDiff::new(&old, &new);
+        assert_eq!(diff.cards_added, ["Inflame", "Inflame"]);
+        assert_eq!(diff.cards_removed, ["Strike_R"]);
+        assert_eq!(diff.cards_upgraded, ["Bash+"]);
+        assert_eq!(diff.relics_gained, ["Vajra"]);
+        assert!(diff.relics_lost.is_empty());
+        assert_eq!((diff.gold_delta, diff.health_delta, diff.max_health_delta), (50, -7, 0));
+        assert_eq!(compose_txt_last_change(&diff), "+2 Inflame, -1 Strike_R, upgraded Bash+, +Vajra, +50 gold, -7 HP\n");
+        assert!(compose_txt_diff_report(&diff).starts_with("Floors advanced: +0\nGold: +50\nHealth: -7\nMax health: +0\n\
+                                                            Cards added: Inflame, Inflame\nCards removed: Strike_R\n"));
+        assert!(SaveDiff::new(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,98 @@
+// 既定のディレクトリ(出力先と設定ファイルの置き場所)を決めるモジュール
+//
+// Linuxなどの Unix系ではXDG Base Directoryに従い、Windowsでは%LOCALAPPDATA%/%APPDATA%を使う
+// 環境変数は引数の関数から読むので、テストでは任意の値を与えられる
+use std::path::{Path, PathBuf};
+
+const APP_DIR_NAME: &str = "sts-observer";
+pub const SETTINGS_FILE_NAME: &str = "settings.json";
+
+// 環境変数の値(空や相対パスはXDGの仕様通り無視する)
+pub fn env_var(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| Path::new(value).is_absolute())
+}
+
+// 最初に設定されている環境変数のディレクトリ(HOMEからの既定値つき)
+fn first_dir(env: &dyn Fn(&str) -> Option<String>, keys: &[&str], home_fallback: Option<&str>) -> Option<PathBuf> {
+    keys.iter()
+        .find_map(|key| env(key).map(PathBuf::from))
+        .or_else(|| Some(PathBuf::from(env("HOME")?).join(home_fallback?)))
+}
+
+/* txtの既定の出力先
+
+  Unix系: $XDG_RUNTIME_DIR/sts-observer/ → $XDG_DATA_HOME/sts-observer/ (既定は~/.local/share)
+  Windows: %LOCALAPPDATA%\sts-observer\
+*/
+pub fn default_output_dir(env: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let base = match cfg!(windows) {
+        true => first_dir(env, &["LOCALAPPDATA"], None),
+        false => first_dir(env, &["XDG_RUNTIME_DIR", "XDG_DATA_HOME"], Some(".local/share")),
+    };
+    base.map(|base| base.join(APP_DIR_NAME))
+}
+
+/* 設定ファイルのディレクトリ
+
+  Unix系: $XDG_CONFIG_HOME/sts-observer/ (既定は~/.config)
+  Windows: %APPDATA%\sts-observer\
+*/
+pub fn config_dir(env: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let base = match cfg!(windows) {
+        true => first_dir(env, &["APPDATA"], None),
+        false => first_dir(env, &["XDG_CONFIG_HOME"], Some(".config")),
+    };
+    base.map(|base| base.join(APP_DIR_NAME))
+}
+
+// 設定ファイルを探す順番: カレントディレクトリ → 設定ディレクトリ → 実行ファイルのディレクトリ
+pub fn settings_candidates(env: &dyn Fn(&str) -> Option<String>, exe_dir: Option<PathBuf>) -> Vec<PathBuf> {
+    let mut candidates = vec![PathBuf::from(".").join(SETTINGS_FILE_NAME)];
+    candidates.extend(config_dir(env).map(|dir| dir.join(SETTINGS_FILE_NAME)));
+    candidates.extend(exe_dir.map(|dir| dir.join(SETTINGS_FILE_NAME)));
+    candidates
+}
+
+pub fn exe_dir() -> Option<PathBuf> {
+    std::env::current_exe().ok()?.parent().map(Path::to_path_buf)
+}
+
+// ディレクトリのパスを末尾に区切り文字のついた文字列にする(ファイル名をそのまま連結するため)
+pub fn dir_string(dir: &Path) -> String {
+    let mut dir = dir.to_string_lossy().to_string();
+    if !dir.ends_with(std::path::MAIN_SEPARATOR) && !dir.ends_with('/') {
+        dir.push(std::path::MAIN_SEPARATOR);
+    }
+    dir
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn env_of(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        |key| vars.iter().find(|(var, _)| *var == key).map(|(_, value)| value.to_string())
+    }
+
+    #[test]
+    fn follows_xdg_variables_with_home_fallbacks() {
+        let env = env_of(&[("HOME", "/home/me"), ("XDG_RUNTIME_DIR", "/run/user/1000")]);
+        assert_eq!(default_output_dir(&env), Some(PathBuf::from("/run/user/1000/sts-observer")));
+        assert_eq!(config_dir(&env), Some(PathBuf::from("/home/me/.config/sts-observer")));
+
+        let env = env_of(&[("HOME", "/home/me")]);
+        assert_eq!(default_output_dir(&env), Some(PathBuf::from("/home/me/.local/share/sts-observer")));
+        assert_eq!(default_output_dir(&env_of(&[])), None);
+    }
+
+    #[test]
+    fn settings_are_searched_in_order() {
+        let env = env_of(&[("XDG_CONFIG_HOME", "/cfg")]);
+        assert_eq!(settings_candidates(&env, Some(PathBuf::from("/opt/sts"))), [
+            PathBuf::from("./settings.json"),
+            PathBuf::from("/cfg/sts-observer/settings.json"),
+            PathBuf::from("/opt/sts/settings.json"),
+        ]);
+        assert_eq!(dir_string(Path::new("/run/user/1000/sts-observer")), "/run/user/1000/sts-observer/");
+    }
+}
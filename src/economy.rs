@@ -0,0 +1,120 @@
+// ゴールドの収支(ショップでの支出・幕ごとの収入・カード削除費用)を扱うモジュール
+//
+// 完了したフロアはmetric_gold_per_floor(フロア終了時の所持金)から、
+// 現在のフロアは最新のautosaveの所持金との差から求める
+use libsts::Save;
+use serde_json::{json, Value};
+
+use crate::timeline::*;
+
+// 開始時の所持金(アセンションによらず共通)
+const STARTING_GOLD: i64 = 99;
+const SHOP_SYMBOL: &str = "$";
+
+// ショップ1回分の支出
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShopVisit {
+    pub floor: u32,
+    pub spent: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Economy {
+    pub gold: i64,
+    pub purge_cost: u32,
+    pub shops: Vec<ShopVisit>,
+    pub income_per_act: Vec<i64>, // 1幕目から順に、フロアごとの増加分の合計
+}
+impl Economy {
+    pub fn new(save: &Save) -> Self {
+        let current_floor = save.floor_num.max(0.0) as u32;
+        // フロアごとの(フロア, 終了時または現在の所持金)
+        let mut golds: Vec<(u32, i64)> = save.metric_gold_per_floor.iter()
+                                                                   .enumerate()
+                                                                   .map(|(i, gold)| (i as u32 + 1, *gold as i64))
+                                                                   .collect();
+        if current_floor > golds.len() as u32 {
+            golds.push((current_floor, save.gold as i64));
+        }
+        let mut shops = Vec::new();
+        let mut income_per_act: Vec<i64> = Vec::new();
+        let mut previous_gold = STARTING_GOLD;
+        for (floor, gold) in golds {
+            let delta = gold - previous_gold;
+            previous_gold = gold;
+            let room = save.metric_path_per_floor.get(floor as usize - 1).cloned().flatten();
+            let in_shop = room.as_deref() == Some(SHOP_SYMBOL) || (floor == current_floor && is_shop_room(save));
+            if in_shop {
+                shops.push(ShopVisit { floor, spent: (-delta).max(0) });
+            }
+            let act = act_of_floor(floor) as usize;
+            if income_per_act.len() < act {
+                income_per_act.resize(act, 0);
+            }
+            income_per_act[act - 1] += delta.max(0);
+        }
+        Self { gold: save.gold as i64, purge_cost: save.purge_cost, shops, income_per_act }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "gold": self.gold,
+            "purge_cost": self.purge_cost,
+            "shops": self.shops.iter().map(|shop| json!({ "floor": shop.floor, "spent": shop.spent })).collect::<Vec<_>>(),
+            "income_per_act": self.income_per_act,
+        })
+    }
+}
+
+fn is_shop_room(save: &Save) -> bool { save.current_room.ends_with("ShopRoom") }
+
+/* sts_economy.txtの内容
+
+  Gold: 187
+  Card removal: 100 gold
+  Shops: floor 9 -120, floor 21 -275
+  Income: Act 1 +312, Act 2 +198
+*/
+pub fn compose_txt_economy(economy: &Economy) -> String {
+    let mut ret = format!("Gold: {}\nCard removal: {} gold\n", economy.gold, economy.purge_cost);
+    let shops: Vec<String> = economy.shops.iter().map(|shop| format!("floor {} -{}", shop.floor, shop.spent)).collect();
+    if shops.is_empty() {
+        ret.push_str("Shops: none yet\n");
+    } else {
+        ret.push_str(&format!("Shops: {}\n", shops.join(", ")));
+    }
+    let income: Vec<String> = economy.income_per_act.iter()
+                                                    .enumerate()
+                                                    .map(|(n, gold)| format!("Act {} +{}", n + 1, gold))
+                                                    .collect();
+    if !income.is_empty() {
+        ret.push_str(&format!("Income: {}\n", income.join(", ")));
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture::*;
+
+    #[test]
+    fn derives_shop_spending_and_income_per_act() {
+        let mut save = example_save();
+        save.purge_cost = 100;
+        save.metric_gold_per_floor = vec![115, 250, 130];
+        save.metric_path_per_floor = vec![Some("M".into()), Some("E".into()), Some("$".into())];
+        // 18階(2幕目)のショップにいて、まだ買い物をしていない
+        save.metric_gold_per_floor.extend(std::iter::repeat_n(130, 14));
+        save.metric_path_per_floor.extend(std::iter::repeat_n(Some("R".to_string()), 14));
+        save.floor_num = 18.0;
+        save.gold = 150;
+        save.current_room = "com.megacrit.cardcrawl.rooms.ShopRoom".to_string();
+
+        let economy = Economy::new(&save);
+        assert_eq!(economy.shops, [ShopVisit { floor: 3, spent: 120 }, ShopVisit { floor: 18, spent: 0 }]);
+        assert_eq!(economy.income_per_act, [151, 20]);
+        assert_eq!(compose_txt_economy(&economy),
+                   "Gold: 150\nCard removal: 100 gold\nShops: floor 3 -120, floor 18 -0\nIncome: Act 1 +151, Act 2 +20\n");
+    }
+}
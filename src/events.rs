@@ -0,0 +1,189 @@
+// Neowのボーナスとイベント(?マス)の選択を扱うモジュール
+use libsts::{EventChoice, Save};
+use serde_json::{json, Value};
+
+// ?マスで戦闘・ショップ・宝箱になる確率(event_chancesの並び順)
+const EVENT_CHANCE_LABELS: [&str; 4] = ["elite", "monster", "shop", "treasure"];
+// Neowの嘆き(次の3戦闘の敵のHPが1)のレリックID
+const NEOWS_LAMENT: &str = "NeowsBlessing";
+
+// ゲーム内部のNeowボーナスIDを表示用の説明に変換する(未知のIDはそのまま)
+pub fn neow_bonus_name(id: &str) -> &str {
+    match id {
+        "THREE_CARDS" => "Choose a card to obtain",
+        "ONE_RANDOM_RARE_CARD" => "Obtain a random rare card",
+        "REMOVE_CARD" => "Remove a card",
+        "UPGRADE_CARD" => "Upgrade a card",
+        "TRANSFORM_CARD" => "Transform a card",
+        "RANDOM_COLORLESS" => "Choose an uncommon colorless card",
+        "THREE_SMALL_POTIONS" => "Obtain 3 random potions",
+        "RANDOM_COMMON_RELIC" => "Obtain a random common relic",
+        "TEN_PERCENT_HP_BONUS" => "Max HP +10%",
+        "THREE_ENEMY_KILL" => "Enemies in the next 3 combats have 1 HP",
+        "HUNDRED_GOLD" => "Obtain 100 gold",
+        "RANDOM_COLORLESS_2" => "Choose a rare colorless card",
+        "REMOVE_TWO" => "Remove 2 cards",
+        "ONE_RARE_RELIC" => "Obtain a random rare relic",
+        "THREE_RARE_CARDS" => "Choose a rare card to obtain",
+        "TWO_FIFTY_GOLD" => "Obtain 250 gold",
+        "TRANSFORM_TWO_CARDS" => "Transform 2 cards",
+        "TWENTY_PERCENT_HP_BONUS" => "Max HP +20%",
+        "BOSS_RELIC" => "Swap the starting relic for a random boss relic",
+        _ => id,
+    }
+}
+// Neowボーナスの代償(なければNone)
+pub fn neow_cost_name(id: &str) -> Option<&str> {
+    match id {
+        "" | "NONE" => None,
+        "TEN_PERCENT_HP_LOSS" => Some("Lose 10% Max HP"),
+        "NO_GOLD" => Some("Lose all gold"),
+        "CURSE" => Some("Obtain a curse"),
+        "PERCENT_DAMAGE" => Some("Take damage (30% of current HP)"),
+        _ => Some(id),
+    }
+}
+
+// 例: "Neow: Obtain 250 gold (cost: Lose 10% Max HP)"
+pub fn compose_txt_neow(save: &Save) -> String {
+    if save.neow_bonus.is_empty() {
+        return String::from("Neow: not chosen yet\n");
+    }
+    match neow_cost_name(&save.neow_cost) {
+        Some(cost) => format!("Neow: {} (cost: {})\n", neow_bonus_name(&save.neow_bonus), cost),
+        None => format!("Neow: {}\n", neow_bonus_name(&save.neow_bonus)),
+    }
+}
+
+// イベントの結果(HP・ゴールドの増減や入手したもの)
+fn event_effects(choice: &EventChoice) -> Vec<String> {
+    let mut effects = Vec::new();
+    let mut push_amount = |amount: f64, sign: &str, unit: &str| {
+        if amount > 0.0 {
+            effects.push(format!("{}{} {}", sign, amount, unit));
+        }
+    };
+    push_amount(choice.damage_healed, "+", "HP");
+    push_amount(choice.damage_taken, "-", "HP");
+    push_amount(choice.max_hp_gain, "+", "Max HP");
+    push_amount(choice.max_hp_loss, "-", "Max HP");
+    push_amount(choice.gold_gain, "+", "gold");
+    push_amount(choice.gold_loss, "-", "gold");
+    let lists = [(&choice.cards_obtained, "+"), (&choice.relics_obtained, "+"), (&choice.cards_upgraded, "upgraded ")];
+    for (items, prefix) in lists {
+        effects.extend(items.iter().flatten().map(|item| format!("{}{}", prefix, item)));
+    }
+    effects
+}
+
+/* sts_events.txtの内容
+
+  Floor 3: Big Fish - Banana (+2 Max HP)
+  Floor 6: Golden Idol - Take (+Golden Idol)
+  Neow's Lament: 2 combats left
+  ? room odds: monster 20%, shop 6%, treasure 4%
+  Events left: 9 (one-time events: 12)
+*/
+pub fn compose_txt_events(save: &Save) -> String {
+    let mut ret = String::new();
+    if save.metric_event_choices.is_empty() {
+        ret.push_str("No events yet\n");
+    }
+    for choice in &save.metric_event_choices {
+        let effects = event_effects(choice);
+        ret.push_str(&format!("Floor {}: {} - {}", choice.floor as i64, choice.event_name, choice.player_choice));
+        if !effects.is_empty() {
+            ret.push_str(&format!(" ({})", effects.join(", ")));
+        }
+        ret.push('\n');
+    }
+    if let Some(combats) = neows_lament_left(save) {
+        ret.push_str(&format!("Neow's Lament: {} combats left\n", combats));
+    }
+    let odds: Vec<String> = EVENT_CHANCE_LABELS.iter()
+                                               .zip(&save.event_chances)
+                                               .filter(|(_, chance)| **chance > 0.0)
+                                               .map(|(label, chance)| format!("{} {}%", label, (chance * 100.0).round()))
+                                               .collect();
+    if !odds.is_empty() {
+        ret.push_str(&format!("? room odds: {}\n", odds.join(", ")));
+    }
+    ret.push_str(&format!("Events left: {} (one-time events: {})\n", save.event_list.len(), save.one_time_event_list.len()));
+    ret
+}
+
+// relic_countersはrelicsと同じ並び
+fn neows_lament_left(save: &Save) -> Option<i32> {
+    let index = save.relics.iter().position(|relic| relic == NEOWS_LAMENT)?;
+    save.relic_counters.get(index).copied().filter(|counter| *counter > 0)
+}
+
+pub fn compose_json_neow(save: &Save) -> Value {
+    json!({ "bonus": save.neow_bonus, "cost": save.neow_cost })
+}
+pub fn compose_json_events(save: &Save) -> Value {
+    save.metric_event_choices.iter()
+        .map(|choice| json!({
+            "floor": choice.floor as i64,
+            "event": choice.event_name,
+            "choice": choice.player_choice,
+            "effects": event_effects(choice),
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture::*;
+
+    fn event(floor: f64, event_name: &str, player_choice: &str) -> EventChoice {
+        EventChoice {
+            cards_upgraded: None,
+            cards_obtained: None,
+            relics_obtained: None,
+            damage_healed: 0.0,
+            damage_taken: 0.0,
+            event_name: event_name.to_string(),
+            floor,
+            gold_gain: 0.0,
+            gold_loss: 0.0,
+            max_hp_gain: 0.0,
+            max_hp_loss: 0.0,
+            player_choice: player_choice.to_string(),
+        }
+    }
+
+    #[test]
+    fn describes_neow_bonus_and_cost() {
+        let mut save = example_save();
+        assert_eq!(compose_txt_neow(&save), "Neow: not chosen yet\n");
+        save.neow_bonus = "TWO_FIFTY_GOLD".to_string();
+        save.neow_cost = "TEN_PERCENT_HP_LOSS".to_string();
+        assert_eq!(compose_txt_neow(&save), "Neow: Obtain 250 gold (cost: Lose 10% Max HP)\n");
+        save.neow_bonus = "THREE_ENEMY_KILL".to_string();
+        save.neow_cost = "NONE".to_string();
+        assert_eq!(compose_txt_neow(&save), "Neow: Enemies in the next 3 combats have 1 HP\n");
+    }
+
+    #[test]
+    fn lists_events_with_effects_and_state() {
+        let mut save = example_save();
+        save.metric_event_choices = vec![
+            EventChoice { max_hp_gain: 2.0, ..event(3.0, "Big Fish", "Banana") },
+            EventChoice { relics_obtained: Some(vec!["Golden Idol".into()]), ..event(6.0, "Golden Idol", "Take") },
+            event(8.0, "Mushrooms", "Ignored"),
+        ];
+        save.relics = vec!["Burning Blood".into(), "NeowsBlessing".into()];
+        save.relic_counters = vec![-1, 2];
+        save.event_chances = vec![0.0, 0.2, 0.06, 0.04];
+        save.event_list = vec!["Big Fish".into()];
+        save.one_time_event_list = vec![];
+        assert_eq!(compose_txt_events(&save), "Floor 3: Big Fish - Banana (+2 Max HP)\n\
+                                                Floor 6: Golden Idol - Take (+Golden Idol)\n\
+                                                Floor 8: Mushrooms - Ignored\n\
+                                                Neow's Lament: 2 combats left\n\
+                                                ? room odds: monster 20%, shop 6%, treasure 4%\n\
+                                                Events left: 1 (one-time events: 0)\n");
+    }
+}
@@ -0,0 +1,251 @@
+// レベル付きのログ出力を扱うモジュール
+//
+// 使い方: error!/warn!/info!/debug!/trace!マクロ(format!と同じ書式、main.rsで#[macro_use])
+// 出力先: 標準出力(warn以上は標準エラー出力)と、任意でローテーション付きのログファイル
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::common::*;
+use crate::system::*;
+
+const DEFAULT_LOG_FILE_MAX_BYTES: u64 = 1024 * 1024;
+const DEFAULT_LOG_FILE_MAX_FILES: u32 = 3;
+
+// ログレベル(値が小さいほど重要)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+impl Level {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "error" => Some(Level::Error),
+            "warn" | "warning" => Some(Level::Warn),
+            "info" => Some(Level::Info),
+            "debug" => Some(Level::Debug),
+            "trace" => Some(Level::Trace),
+            _ => None,
+        }
+    }
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+            Level::Trace => "trace",
+        }
+    }
+}
+
+// ログの書式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+// ログファイルの出力先とローテーション条件
+#[derive(Debug, Clone, PartialEq)]
+struct LogFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Logger {
+    level: Level,
+    format: Format,
+    file: Option<LogFile>,
+}
+
+impl Logger {
+    fn enabled(&self, level: Level) -> bool {
+        level <= self.level
+    }
+}
+
+static LOGGER: Mutex<Logger> = Mutex::new(Logger { level: Level::Info, format: Format::Text, file: None });
+
+/* settings.jsonのログ設定を反映する
+
+  log_level: "error" | "warn" | "info" | "debug" | "trace" (既定: "info")
+  log_format: "text" | "json" (既定: "text")
+  log_file_path: ログファイルのパス(未指定ならファイル出力なし)
+  log_file_max_bytes / log_file_max_files: ローテーションの条件
+*/
+pub fn configure(json_data: &JsonData) -> Result<(), String> {
+    let level = match json_data.get_value_from_key::<String>("log_level") {
+        Some(name) => Level::parse(&name).ok_or_else(|| format!("unknown log_level: {}", name))?,
+        None => Level::Info,
+    };
+    let format = match json_data.get_value_from_key::<String>("log_format").as_deref() {
+        None | Some("text") => Format::Text,
+        Some("json") => Format::Json,
+        Some(name) => return Err(format!("unknown log_format: {}", name)),
+    };
+    let file = json_data.get_path_from_key("log_file_path").map(|path| LogFile {
+        path,
+        max_bytes: json_data.get_value_from_key("log_file_max_bytes").unwrap_or(DEFAULT_LOG_FILE_MAX_BYTES),
+        max_files: json_data.get_value_from_key("log_file_max_files").unwrap_or(DEFAULT_LOG_FILE_MAX_FILES),
+    });
+    let mut logger = LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+    *logger = Logger { level, format, file };
+    Ok(())
+}
+
+// マクロから呼ばれる本体
+pub fn log(level: Level, args: std::fmt::Arguments) {
+    let logger = LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+    if !logger.enabled(level) {
+        return;
+    }
+    let line = format_line(logger.format, level, &format_utc(now_secs()), args);
+    if level <= Level::Warn {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+    if let Some(file) = &logger.file {
+        // ログファイルへの書き出し失敗でログを出すと再帰するので標準エラー出力のみに報告
+        if let Err(e) = append_to_file(&RealFileSystem, file, &line) {
+            eprintln!("Failed to write log file {}: {}", file.path.display(), e);
+        }
+    }
+}
+
+// 1行分のログ(text: "時刻 レベル メッセージ"、json: {"ts", "level", "msg"})
+fn format_line(format: Format, level: Level, timestamp: &str, args: std::fmt::Arguments) -> String {
+    match format {
+        Format::Text => format!("{} {:<5} {}", timestamp, level.as_str().to_ascii_uppercase(), args),
+        Format::Json => json!({ "ts": timestamp, "level": level.as_str(), "msg": args.to_string() }).to_string(),
+    }
+}
+
+fn append_to_file(fs: &impl FileSystem, file: &LogFile, line: &str) -> std::io::Result<()> {
+    if fs.metadata(&file.path).is_ok_and(|meta| meta.len >= file.max_bytes) {
+        rotate(fs, &file.path, file.max_files)?;
+    }
+    fs.append(&file.path, format!("{}\n", line).as_bytes())
+}
+
+// app.log -> app.log.1 -> app.log.2 ... (max_files個を超えた分は削除)
+fn rotate(fs: &impl FileSystem, path: &Path, max_files: u32) -> std::io::Result<()> {
+    let numbered = |n: u32| {
+        let mut name = path.as_os_str().to_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+    if max_files == 0 {
+        return fs.remove_file(path);
+    }
+    let _ = fs.remove_file(&numbered(max_files));
+    for n in (1..max_files).rev() {
+        if fs.is_file(&numbered(n)) {
+            fs.rename(&numbered(n), &numbered(n + 1))?;
+        }
+    }
+    fs.rename(path, &numbered(1))
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// UNIX時間(秒)をISO 8601形式(UTC)の文字列に変換する
+pub fn format_utc(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let rem = secs % 86400;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, rem / 3600, rem / 60 % 60, rem % 60)
+}
+
+// 1970-01-01からの日数を(年, 月, 日)に変換する
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => { $crate::logger::log($crate::logger::Level::Error, format_args!($($arg)*)) };
+}
+macro_rules! warn {
+    ($($arg:tt)*) => { $crate::logger::log($crate::logger::Level::Warn, format_args!($($arg)*)) };
+}
+macro_rules! info {
+    ($($arg:tt)*) => { $crate::logger::log($crate::logger::Level::Info, format_args!($($arg)*)) };
+}
+macro_rules! debug {
+    ($($arg:tt)*) => { $crate::logger::log($crate::logger::Level::Debug, format_args!($($arg)*)) };
+}
+macro_rules! trace {
+    ($($arg:tt)*) => { $crate::logger::log($crate::logger::Level::Trace, format_args!($($arg)*)) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fake::*;
+    use serde_json::Value;
+
+    #[test]
+    fn formats_utc_timestamps() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(1709600461), "2024-03-05T01:01:01Z");
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn filters_by_level() {
+        assert_eq!(Level::parse("WARNING"), Some(Level::Warn));
+        assert_eq!(Level::parse("verbose"), None);
+        let logger = Logger { level: Level::Info, format: Format::Text, file: None };
+        assert!(logger.enabled(Level::Error) && logger.enabled(Level::Info));
+        assert!(!logger.enabled(Level::Debug) && !logger.enabled(Level::Trace));
+    }
+
+    #[test]
+    fn formats_text_and_json_lines() {
+        let ts = "2024-03-05T01:01:01Z";
+        assert_eq!(format_line(Format::Text, Level::Warn, ts, format_args!("Save failed: {}", "map txt")),
+                   "2024-03-05T01:01:01Z WARN  Save failed: map txt");
+        let line = format_line(Format::Json, Level::Info, ts, format_args!("Mode transition: {}", "a \"b\""));
+        let json: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json, json!({ "ts": ts, "level": "info", "msg": "Mode transition: a \"b\"" }));
+    }
+
+    #[test]
+    fn rotates_log_file_by_size() {
+        let fs = MemFileSystem::default();
+        let file = LogFile { path: PathBuf::from("/log/sts.log"), max_bytes: 10, max_files: 2 };
+        for line in ["first line", "second", "third line", "fourth"] {
+            append_to_file(&fs, &file, line).unwrap();
+        }
+        // 10バイト以上になったら次の書き込みの前にずらす
+        assert_eq!(fs.contents("/log/sts.log").as_deref(), Some("fourth\n"));
+        assert_eq!(fs.contents("/log/sts.log.1").as_deref(), Some("second\nthird line\n"));
+        assert_eq!(fs.contents("/log/sts.log.2").as_deref(), Some("first line\n"));
+
+        // 2世代より古いものは消す
+        append_to_file(&fs, &LogFile { max_bytes: 1, ..file.clone() }, "fifth").unwrap();
+        assert_eq!(fs.contents("/log/sts.log.2").as_deref(), Some("second\nthird line\n"));
+        assert_eq!(fs.contents("/log/sts.log.3"), None);
+    }
+}
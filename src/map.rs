@@ -0,0 +1,281 @@
+// 進んだマップの経路と現在地を扱うモジュール
+//
+// autosaveにはマップ全体はなく、通ったノードの座標(path_x/path_y)と現在地(room_x/room_y)、
+// フロアごとの部屋の種類(metric_path_taken/metric_path_per_floor)だけが記録されている
+// マップ全体はシードから生成し直し(map_gen)、通った経路と食い違うとき(4幕目など)は経路だけを描く
+use libsts::Save;
+use serde_json::{json, Value};
+
+use crate::common::plural;
+use crate::map_gen::*;
+
+const BOSS_SYMBOL: &str = "BOSS";
+
+// 経路上のノード
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathNode {
+    pub x: i32,
+    pub y: i32,
+    pub symbol: String, // マップ上の表示(M, ?, $, R, E, T)
+}
+
+// 幕ごとの部屋の種類の集計(?マスは実際に入った部屋で数える)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RoomCounts {
+    pub monsters: u32,
+    pub elites: u32,
+    pub events: u32,
+    pub shops: u32,
+    pub rests: u32,
+    pub treasures: u32,
+}
+impl RoomCounts {
+    fn add(&mut self, symbol: &str) {
+        match symbol {
+            "M" => self.monsters += 1,
+            "E" => self.elites += 1,
+            "?" => self.events += 1,
+            "$" => self.shops += 1,
+            "R" => self.rests += 1,
+            "T" => self.treasures += 1,
+            _ => {},
+        }
+    }
+    fn to_json(self) -> Value {
+        json!({
+            "monsters": self.monsters, "elites": self.elites, "events": self.events,
+            "shops": self.shops, "rests": self.rests, "treasures": self.treasures,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapPath {
+    pub act: u32,
+    pub acts: Vec<Vec<PathNode>>, // 幕ごとに通ったノード(yが戻ったら次の幕)
+    pub current: Option<(i32, i32)>,
+    pub room_counts: Vec<RoomCounts>,
+    pub act_map: Option<ActMap>, // シードから生成した現在の幕のマップ
+}
+impl MapPath {
+    pub fn new(save: &Save) -> Self {
+        // ボスはpath_takenにだけ現れることがあるので、グリッド上のノードと揃えるために除く
+        let mut symbols = save.metric_path_taken.iter().filter(|symbol| *symbol != BOSS_SYMBOL);
+        let mut acts: Vec<Vec<PathNode>> = vec![Vec::new()];
+        for (&x, &y) in save.path_x.iter().zip(&save.path_y) {
+            if !on_grid(x, y) {
+                continue;
+            }
+            let symbol = symbols.next().cloned().unwrap_or_else(|| "?".to_string());
+            if acts.last().unwrap().last().is_some_and(|last| y <= last.y) {
+                acts.push(Vec::new());
+            }
+            acts.last_mut().unwrap().push(PathNode { x, y, symbol });
+        }
+        // 幕の区切りはボス部屋
+        let mut room_counts = vec![RoomCounts::default()];
+        for symbol in save.metric_path_per_floor.iter().flatten() {
+            if symbol == BOSS_SYMBOL || symbol == "B" {
+                room_counts.push(RoomCounts::default());
+            } else {
+                room_counts.last_mut().unwrap().add(symbol);
+            }
+        }
+        let current = (save.room_y >= 0).then_some((save.room_x, save.room_y));
+        let mut map_path = Self { act: save.act_num, acts, current, room_counts, act_map: None };
+        let ascension_level = if save.is_ascension_mode { save.ascension_level } else { 0 };
+        let mods: Vec<String> = save.custom_mods.iter().chain(&save.daily_mods).cloned().collect();
+        map_path.act_map = generate_act_map(save.seed, save.act_num, ascension_level, &mods)
+            .filter(|act_map| map_path.follows(act_map));
+        map_path
+    }
+
+    // 現在の幕で通ったノード(幕の最初の部屋に入る前は空)
+    pub fn current_act_nodes(&self) -> &[PathNode] {
+        self.acts.get(self.act.saturating_sub(1) as usize).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    // 生成したマップが通った経路と現在地に合っているか(合わなければ経路だけを描く)
+    fn follows(&self, act_map: &ActMap) -> bool {
+        let nodes = self.current_act_nodes();
+        let on_map = |x: i32, y: i32| y >= MAP_HEIGHT || act_map.room(x, y).is_some();
+        nodes.iter().all(|node| on_map(node.x, node.y))
+            && nodes.windows(2).all(|pair| act_map.edges(pair[0].x, pair[0].y).contains(&(pair[1].x, pair[1].y)))
+            && self.current.is_none_or(|(x, y)| on_map(x, y))
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "path": self.current_act_nodes().iter().map(|node| json!([node.x, node.y])).collect::<Vec<_>>(),
+            "current": self.current.map(|(x, y)| json!({ "x": x, "y": y })),
+            "rooms_per_act": self.room_counts.iter().map(|counts| counts.to_json()).collect::<Vec<_>>(),
+            "map": self.act_map.as_ref().map(act_map_to_json),
+        })
+    }
+}
+
+fn on_grid(x: i32, y: i32) -> bool { (0..MAP_WIDTH).contains(&x) && (0..MAP_HEIGHT).contains(&y) }
+
+/* sts_map.txtの内容: 現在の幕のマップ(上がボス側)と幕ごとの部屋の集計
+
+          BOSS
+   R     R  R        R
+   |   /    |      /
+   ?     E  M    (?)        <- ( )が通った経路
+  ...
+  [M]    M     M            <- [ ]が現在地
+  Act 1: 3 monsters, 1 elite, ...
+
+  生成したマップが使えないときは通ったノードだけを描き、他の位置は"."にする
+*/
+pub fn compose_txt_map(save: &Save, map_path: &MapPath) -> String {
+    let nodes = map_path.current_act_nodes();
+    let mut ret = format!("Act {} / Floor {}\n", save.act_num, save.floor_num as i64);
+    let boss_here = map_path.current.is_some_and(|(_, y)| y >= MAP_HEIGHT);
+    let boss = if boss_here { "[BOSS]" } else { " BOSS " };
+    ret.push_str(format!("{:^width$}", boss, width = (MAP_WIDTH * 3) as usize).trim_end());
+    ret.push('\n');
+    for y in (0..MAP_HEIGHT).rev() {
+        let row: String = (0..MAP_WIDTH)
+            .map(|x| {
+                let visited = nodes.iter().find(|node| node.x == x && node.y == y);
+                let symbol = match &map_path.act_map {
+                    Some(act_map) => act_map.room(x, y).map_or(" ", |room| room.symbol()),
+                    None => visited.map_or(".", |node| &node.symbol),
+                };
+                if map_path.current == Some((x, y)) {
+                    format!("[{}]", symbol)
+                } else if visited.is_some() && map_path.act_map.is_some() {
+                    format!("({})", symbol)
+                } else {
+                    format!(" {} ", symbol)
+                }
+            })
+            .collect();
+        ret.push_str(row.trim_end());
+        ret.push('\n');
+        if let (Some(act_map), true) = (&map_path.act_map, y > 0) {
+            ret.push_str(&compose_edge_row(act_map, y - 1));
+            ret.push('\n');
+        }
+    }
+    for (n, counts) in map_path.room_counts.iter().enumerate() {
+        ret.push_str(&format!(
+            "Act {}: {} {}, {} {}, {} {}, {} {}, {} {}, {} {}\n",
+            n + 1,
+            counts.monsters, plural(counts.monsters, "monster"),
+            counts.elites, plural(counts.elites, "elite"),
+            counts.events, plural(counts.events, "event"),
+            counts.shops, plural(counts.shops, "shop"),
+            counts.rests, plural(counts.rests, "rest"),
+            counts.treasures, plural(counts.treasures, "treasure"),
+        ));
+    }
+    ret
+}
+
+// y行目のノードから上の行への経路(ノードの表示と同じ3文字幅の格子に描く)
+fn compose_edge_row(act_map: &ActMap, y: i32) -> String {
+    let mut row = vec![' '; (MAP_WIDTH * 3) as usize];
+    for x in 0..MAP_WIDTH {
+        for &(dst_x, _) in act_map.edges(x, y) {
+            let (column, line) = match dst_x - x {
+                -1 => (3 * x, '\\'),
+                1 => (3 * x + 2, '/'),
+                _ => (3 * x + 1, '|'),
+            };
+            row[column as usize] = line;
+        }
+    }
+    row.into_iter().collect::<String>().trim_end().to_string()
+}
+
+fn act_map_to_json(act_map: &ActMap) -> Value {
+    let nodes: Vec<Value> = (0..MAP_HEIGHT)
+        .flat_map(|y| (0..MAP_WIDTH).map(move |x| (x, y)))
+        .filter_map(|(x, y)| {
+            let room = act_map.room(x, y)?;
+            let edges: Vec<Value> = act_map.edges(x, y).iter().map(|(dst_x, dst_y)| json!([dst_x, dst_y])).collect();
+            Some(json!({ "x": x, "y": y, "room": room.symbol(), "edges": edges }))
+        })
+        .collect();
+    json!(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture::*;
+
+    // 1幕目を3フロア進み、2幕目の最初のマスにいる状態
+    fn save() -> Save {
+        let mut save = example_save();
+        save.act_num = 2;
+        save.floor_num = 18.0;
+        save.path_x = vec![0, 1, 1, 3];
+        save.path_y = vec![0, 1, 2, 0];
+        save.room_x = 3;
+        save.room_y = 0;
+        save.metric_path_taken = ["M", "?", "R", "BOSS", "M"].map(String::from).to_vec();
+        save.metric_path_per_floor = vec![Some("M".into()), Some("E".into()), Some("R".into()), Some("B".into()), None];
+        save
+    }
+
+    #[test]
+    fn splits_path_into_acts_and_counts_rooms() {
+        let map_path = MapPath::new(&save());
+        assert_eq!(map_path.acts.len(), 2);
+        assert_eq!(map_path.acts[0][1], PathNode { x: 1, y: 1, symbol: "?".to_string() });
+        assert_eq!(map_path.acts[1], [PathNode { x: 3, y: 0, symbol: "M".to_string() }]);
+        assert_eq!(map_path.room_counts[0], RoomCounts { monsters: 1, elites: 1, rests: 1, ..RoomCounts::default() });
+        assert_eq!(map_path.room_counts[1], RoomCounts::default());
+    }
+
+    #[test]
+    fn renders_path_only_when_it_does_not_match_the_generated_map() {
+        let save = save();
+        let map_path = MapPath::new(&save);
+        // 2幕目の生成マップには(3, 0)のノードがないので、通ったノードだけを描く
+        assert_eq!(map_path.act_map, None);
+        let txt = compose_txt_map(&save, &map_path);
+        let lines: Vec<&str> = txt.lines().collect();
+        assert_eq!(lines[0], "Act 2 / Floor 18");
+        assert_eq!(lines[1], "        BOSS");
+        assert_eq!(lines[2 + 14], " .  .  . [M] .  .  .");
+        assert_eq!(lines[2 + 13], " .  .  .  .  .  .  .");
+        assert_eq!(lines[17], "Act 1: 1 monster, 1 elite, 0 events, 0 shops, 1 rest, 0 treasures");
+        assert_eq!(lines.len(), 19);
+    }
+
+    #[test]
+    fn renders_generated_act_map_with_taken_path() {
+        let mut save = example_save();
+        save.floor_num = 3.0;
+        save.path_x = vec![0, 1, 1];
+        save.path_y = vec![0, 1, 2];
+        save.room_x = 1;
+        save.room_y = 2;
+        save.metric_path_taken = ["M", "?", "M"].map(String::from).to_vec();
+        let map_path = MapPath::new(&save);
+        assert!(map_path.act_map.is_some());
+        let txt = compose_txt_map(&save, &map_path);
+        let lines: Vec<&str> = txt.lines().collect();
+        assert_eq!(lines[1], "        BOSS");
+        assert_eq!(lines[2], "       R        R  R");
+        assert_eq!(lines[3], "       |      / | \\|");
+        assert_eq!(lines[26], "   [M]       M  $  ?");
+        assert_eq!(lines[27], "  / |          \\|/");
+        assert_eq!(lines[28], " ? (?)          ?");
+        assert_eq!(lines[29], "  /\\          /");
+        assert_eq!(lines[30], "(M) M        M");
+        assert_eq!(lines.len(), 32);
+        assert_eq!(map_path.to_json()["map"][0], json!({ "x": 0, "y": 0, "room": "M", "edges": [[1, 1]] }));
+
+        // 通ったノードが生成したマップにない(カスタムモードのモディファイアなど)
+        save.path_x = vec![6];
+        save.path_y = vec![0];
+        save.room_x = 6;
+        save.room_y = 0;
+        assert_eq!(MapPath::new(&save).act_map, None);
+    }
+}
@@ -0,0 +1,426 @@
+// シードから幕のマップ(ノードと経路、部屋の種類)を再現するモジュール
+//
+// autosaveにはマップ全体が入っていないので、ゲームのマップ生成
+// (MapGenerator.generateDungeon → RoomTypeAssigner.distributeRoomsAcrossMap)を同じ乱数列でなぞる
+// ゲーム側の癖(getCommonAncestorでxとyを比べている等)も結果が変わらないようにそのまま再現している
+//
+// 乱数: マップ用のRandomはlibGDXのRandomXS128、部屋の並べ替え(Collections.shuffle)はjava.util.Random
+
+pub const MAP_WIDTH: i32 = 7;
+pub const MAP_HEIGHT: i32 = 15; // y=15はボス
+const PATH_DENSITY: i32 = 6;
+const BOSS_X: i32 = 3;
+// 共通の祖先がこの階数より近い経路は合流させない
+const MIN_ANCESTOR_GAP: i32 = 3;
+const MAX_ANCESTOR_GAP: i32 = 5;
+
+// 1〜3幕の部屋の出現率
+const SHOP_ROOM_CHANCE: f32 = 0.05;
+const REST_ROOM_CHANCE: f32 = 0.12;
+const TREASURE_ROOM_CHANCE: f32 = 0.0;
+const EVENT_ROOM_CHANCE: f32 = 0.22;
+const ELITE_ROOM_CHANCE: f32 = 0.08;
+
+// マップ生成を変えるモディファイア
+const ELITE_SWARM: &str = "Elite Swarm";
+const UNCERTAIN_FUTURE: &str = "Uncertain Future";
+
+// 部屋の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Room {
+    Monster,
+    Elite,
+    Event,
+    Shop,
+    Rest,
+    Treasure,
+}
+impl Room {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Room::Monster => "M",
+            Room::Elite => "E",
+            Room::Event => "?",
+            Room::Shop => "$",
+            Room::Rest => "R",
+            Room::Treasure => "T",
+        }
+    }
+}
+
+// マップ上のノード(edgesは行き先の座標をx, yの順に並べたもの)
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Node {
+    edges: Vec<(i32, i32)>,
+    parents: Vec<(i32, i32)>, // ゲームと同じく重複を許す(経路の調整で乱数を引く回数に影響する)
+    room: Option<Room>,
+}
+impl Node {
+    fn add_edge(&mut self, dst: (i32, i32)) {
+        if !self.edges.contains(&dst) {
+            self.edges.push(dst);
+            self.edges.sort();
+        }
+    }
+}
+
+// 生成された幕のマップ
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActMap {
+    rows: Vec<Vec<Node>>, // rows[y][x]
+}
+#[rustfmt::skip]
+impl ActMap {
+    fn node(&self, (x, y): (i32, i32)) -> &Node { &self.rows[y as usize][x as usize] }
+    fn node_mut(&mut self, (x, y): (i32, i32)) -> &mut Node { &mut self.rows[y as usize][x as usize] }
+    // 経路のつながったノードの部屋(つながっていないノードはNone)
+    pub fn room(&self, x: i32, y: i32) -> Option<Room> {
+        let node = self.rows.get(y as usize)?.get(x as usize)?;
+        node.room.filter(|_| !node.edges.is_empty())
+    }
+    // ノードから上の行への経路(一番上の行からはボスへ)
+    pub fn edges(&self, x: i32, y: i32) -> &[(i32, i32)] { &self.node((x, y)).edges }
+}
+
+/* 幕のマップを生成する(生成方法が分からない4幕目はNone)
+
+  マップ用の乱数のシード: 1幕目はseed+1、2幕目はseed+200、3幕目はseed+600
+*/
+pub fn generate_act_map(seed: i64, act: u32, ascension_level: u32, mods: &[String]) -> Option<ActMap> {
+    let offset = match act {
+        1 => 1,
+        2 => 200,
+        3 => 600,
+        _ => return None,
+    };
+    let mut rng = StsRandom::new(seed.wrapping_add(offset));
+    let has_mod = |id: &str| mods.iter().any(|mod_id| mod_id == id);
+    let path_density = if has_mod(UNCERTAIN_FUTURE) { 1 } else { PATH_DENSITY };
+    let mut map = ActMap { rows: vec![vec![Node::default(); MAP_WIDTH as usize]; MAP_HEIGHT as usize] };
+    create_paths(&mut map, path_density, &mut rng);
+    filter_redundant_edges_from_first_row(&mut map);
+
+    // ボス手前の行(y=13)は数えない(ゲームと同じ)
+    let room_count = map.rows.iter()
+                             .enumerate()
+                             .filter(|(y, _)| *y as i32 != MAP_HEIGHT - 2)
+                             .flat_map(|(_, row)| row)
+                             .filter(|node| !node.edges.is_empty())
+                             .count();
+    let mut room_list = room_types(room_count, ascension_level, has_mod(ELITE_SWARM));
+    assign_row(&mut map, MAP_HEIGHT - 1, Room::Rest);
+    assign_row(&mut map, 0, Room::Monster);
+    assign_row(&mut map, 8, Room::Treasure);
+    distribute_rooms(&mut map, &mut room_list, &mut rng);
+    Some(map)
+}
+
+fn create_paths(map: &mut ActMap, path_density: i32, rng: &mut StsRandom) {
+    let mut first_start = -1;
+    for i in 0..path_density {
+        let mut start = rng.range(0, MAP_WIDTH - 1);
+        if i == 0 {
+            first_start = start;
+        }
+        // 2本目の経路は1本目と別のノードから始める
+        while start == first_start && i == 1 {
+            start = rng.range(0, MAP_WIDTH - 1);
+        }
+        create_path(map, start, rng);
+    }
+}
+
+// 1本の経路を一番下の行からボスまで伸ばす
+fn create_path(map: &mut ActMap, start_x: i32, rng: &mut StsRandom) {
+    let row_end = MAP_WIDTH - 1;
+    let (mut x, mut y) = (start_x, 0);
+    while y + 1 < MAP_HEIGHT {
+        let (min, max) = match x {
+            0 => (0, 1),
+            _ if x == row_end => (-1, 0),
+            _ => (-1, 1),
+        };
+        let mut new_x = x + rng.range(min, max);
+        let new_y = y + 1;
+
+        // 行き先のノードに別の親から来ている経路があり、共通の祖先が近すぎれば行き先をずらす
+        let mut candidate_x = new_x;
+        for parent in map.node((new_x, new_y)).parents.clone() {
+            if parent == (x, y) {
+                continue;
+            }
+            let Some(ancestor) = common_ancestor(map, parent, (x, y), MAX_ANCESTOR_GAP) else {
+                continue;
+            };
+            if new_y - ancestor.1 >= MIN_ANCESTOR_GAP {
+                continue;
+            }
+            if candidate_x > x {
+                new_x = x + rng.range(-1, 0);
+                if new_x < 0 {
+                    new_x = x;
+                }
+            } else if candidate_x == x {
+                new_x = x + rng.range(-1, 1);
+                if new_x > row_end {
+                    new_x = x - 1;
+                } else if new_x < 0 {
+                    new_x = x + 1;
+                }
+            } else {
+                new_x = x + rng.range(0, 1);
+                if new_x > row_end {
+                    new_x = x;
+                }
+            }
+            candidate_x = new_x;
+        }
+        // 隣のノードの経路と交差しないようにする
+        if y != 0 {
+            if x != 0 {
+                if let Some(&(left_max, _)) = map.node((x - 1, y)).edges.last() {
+                    new_x = new_x.max(left_max);
+                }
+            }
+            if x < row_end {
+                if let Some(&(right_min, _)) = map.node((x + 1, y)).edges.first() {
+                    new_x = new_x.min(right_min);
+                }
+            }
+        }
+        map.node_mut((x, y)).add_edge((new_x, new_y));
+        map.node_mut((new_x, new_y)).parents.push((x, y));
+        (x, y) = (new_x, new_y);
+    }
+    // 一番上の行からはボスへ
+    map.node_mut((x, y)).add_edge((BOSS_X, y + 2));
+}
+
+// 2つのノードの共通の祖先(max_depth行まで遡る)
+fn common_ancestor(map: &ActMap, node1: (i32, i32), node2: (i32, i32), max_depth: i32) -> Option<(i32, i32)> {
+    // ゲームではnode1のxとnode2のyを比べている(左右の判定としては誤りだが結果を合わせるためそのまま)
+    let (mut left, mut right) = if node1.0 < node2.1 { (node1, node2) } else { (node2, node1) };
+    let mut current_y = node1.1;
+    while current_y >= 0 && current_y >= node1.1 - max_depth {
+        let left_parents = &map.node(left).parents;
+        let right_parents = &map.node(right).parents;
+        if left_parents.is_empty() || right_parents.is_empty() {
+            return None;
+        }
+        left = *left_parents.iter().reduce(|max, node| if node.0 > max.0 { node } else { max }).unwrap();
+        right = *right_parents.iter().reduce(|min, node| if node.0 < min.0 { node } else { min }).unwrap();
+        if left == right {
+            return Some(left);
+        }
+        current_y -= 1;
+    }
+    None
+}
+
+// 一番下の行で同じノードへ向かう経路を1本にまとめる(親の記録は残る)
+fn filter_redundant_edges_from_first_row(map: &mut ActMap) {
+    let mut existing: Vec<(i32, i32)> = Vec::new();
+    for node in map.rows[0].iter_mut() {
+        let mut deleted: Vec<(i32, i32)> = Vec::new();
+        for &edge in &node.edges {
+            if existing.contains(&edge) {
+                deleted.push(edge);
+            }
+            existing.push(edge);
+        }
+        node.edges.retain(|edge| !deleted.contains(edge));
+    }
+}
+
+// 配置する部屋の一覧(残りは後から通常の敵で埋める)
+fn room_types(room_count: usize, ascension_level: u32, elite_swarm: bool) -> Vec<Room> {
+    // Math.round(float)と同じ丸め
+    let round = |value: f32| (value + 0.5).floor() as usize;
+    let count = room_count as f32;
+    let elite_count = if elite_swarm {
+        round(count * (ELITE_ROOM_CHANCE * 2.5))
+    } else if ascension_level >= 1 {
+        round(count * ELITE_ROOM_CHANCE * 1.6)
+    } else {
+        round(count * ELITE_ROOM_CHANCE)
+    };
+    let mut rooms = Vec::new();
+    rooms.extend(std::iter::repeat_n(Room::Shop, round(count * SHOP_ROOM_CHANCE)));
+    rooms.extend(std::iter::repeat_n(Room::Rest, round(count * REST_ROOM_CHANCE)));
+    rooms.extend(std::iter::repeat_n(Room::Treasure, round(count * TREASURE_ROOM_CHANCE)));
+    rooms.extend(std::iter::repeat_n(Room::Elite, elite_count));
+    rooms.extend(std::iter::repeat_n(Room::Event, round(count * EVENT_ROOM_CHANCE)));
+    rooms
+}
+
+fn assign_row(map: &mut ActMap, y: i32, room: Room) {
+    for node in map.rows[y as usize].iter_mut() {
+        node.room.get_or_insert(room);
+    }
+}
+
+// 部屋を並べ替えて、下の行から規則に合う最初の部屋を割り当てる
+fn distribute_rooms(map: &mut ActMap, room_list: &mut Vec<Room>, rng: &mut StsRandom) {
+    let node_count = map.rows.iter().flatten().filter(|node| !node.edges.is_empty() && node.room.is_none()).count();
+    while room_list.len() < node_count {
+        room_list.push(Room::Monster);
+    }
+    let mut shuffle_rng = JavaRandom::new(rng.next_long());
+    for i in (2..=room_list.len()).rev() {
+        let j = shuffle_rng.next_int(i as i32) as usize;
+        room_list.swap(i - 1, j);
+    }
+    for y in 0..MAP_HEIGHT {
+        for x in 0..MAP_WIDTH {
+            let node = map.node((x, y));
+            if node.edges.is_empty() || node.room.is_some() {
+                continue;
+            }
+            if let Some(i) = room_list.iter().position(|&room| room_fits(map, (x, y), room)) {
+                map.node_mut((x, y)).room = Some(room_list.remove(i));
+            }
+        }
+    }
+    // 規則に合う部屋がなかったノードは通常の敵
+    for node in map.rows.iter_mut().flatten() {
+        if !node.edges.is_empty() && node.room.is_none() {
+            node.room = Some(Room::Monster);
+        }
+    }
+}
+
+/* 部屋を置けるかどうか
+
+  - 休憩とエリートは下から5行目まで置かない、休憩はボス手前の2行にも置かない
+  - 休憩・宝箱・ショップ・エリートは親と同じ種類にしない
+  - 宝箱以外は同じ親を持つ兄弟と同じ種類にしない
+*/
+fn room_fits(map: &ActMap, pos: (i32, i32), room: Room) -> bool {
+    let y = pos.1;
+    if y <= 4 && matches!(room, Room::Rest | Room::Elite) {
+        return false;
+    }
+    if y >= 13 && room == Room::Rest {
+        return false;
+    }
+    if y == 0 {
+        return true;
+    }
+    let parents = &map.node(pos).parents;
+    let parent_matches = matches!(room, Room::Rest | Room::Treasure | Room::Shop | Room::Elite)
+        && parents.iter().any(|&parent| map.node(parent).room == Some(room));
+    let sibling_matches = room != Room::Treasure
+        && parents.iter()
+                  .flat_map(|&parent| map.node(parent).edges.iter())
+                  .any(|&sibling| sibling != pos && map.node(sibling).room == Some(room));
+    !parent_matches && !sibling_matches
+}
+
+// ゲームのRandom(libGDXのRandomXS128を包んだもの)
+struct StsRandom {
+    seed0: u64,
+    seed1: u64,
+}
+impl StsRandom {
+    fn new(seed: i64) -> Self {
+        let seed = if seed == 0 { i64::MIN } else { seed };
+        let seed0 = murmur_hash3(seed as u64);
+        Self { seed0, seed1: murmur_hash3(seed0) }
+    }
+    fn next_long(&mut self) -> i64 {
+        let mut s1 = self.seed0;
+        let s0 = self.seed1;
+        self.seed0 = s0;
+        s1 ^= s1 << 23;
+        self.seed1 = s1 ^ s0 ^ (s1 >> 17) ^ (s0 >> 26);
+        self.seed1.wrapping_add(s0) as i64
+    }
+    // 0以上n未満
+    fn next_int(&mut self, n: i32) -> i32 {
+        let n = n as i64;
+        loop {
+            let bits = ((self.next_long() as u64) >> 1) as i64;
+            let value = bits % n;
+            if bits.wrapping_sub(value).wrapping_add(n - 1) >= 0 {
+                return value as i32;
+            }
+        }
+    }
+    // min以上max以下
+    fn range(&mut self, min: i32, max: i32) -> i32 { min + self.next_int(max - min + 1) }
+}
+
+fn murmur_hash3(mut x: u64) -> u64 {
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    x ^= x >> 33;
+    x
+}
+
+// java.util.Random(線形合同法)
+struct JavaRandom {
+    seed: i64,
+}
+impl JavaRandom {
+    const MULTIPLIER: i64 = 0x5_DEEC_E66D;
+    const MASK: i64 = (1 << 48) - 1;
+
+    fn new(seed: i64) -> Self { Self { seed: (seed ^ Self::MULTIPLIER) & Self::MASK } }
+    fn next(&mut self, bits: u32) -> i32 {
+        self.seed = self.seed.wrapping_mul(Self::MULTIPLIER).wrapping_add(0xB) & Self::MASK;
+        (self.seed >> (48 - bits)) as i32
+    }
+    // 0以上bound未満
+    fn next_int(&mut self, bound: i32) -> i32 {
+        if bound & -bound == bound {
+            return ((bound as i64 * self.next(31) as i64) >> 31) as i32;
+        }
+        loop {
+            let bits = self.next(31);
+            let value = bits % bound;
+            if bits.wrapping_sub(value).wrapping_add(bound - 1) >= 0 {
+                return value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn java_random_matches_the_jdk() {
+        assert_eq!(JavaRandom::new(0).next(32), -1155484576);
+        assert_eq!(JavaRandom::new(42).next(32), -1170105035);
+    }
+
+    #[test]
+    fn generated_map_follows_the_game_rules() {
+        let map = generate_act_map(1234567890123, 1, 1, &[]).unwrap();
+        for y in 0..MAP_HEIGHT {
+            for x in 0..MAP_WIDTH {
+                let Some(room) = map.room(x, y) else { continue };
+                match y {
+                    0 => assert_eq!(room, Room::Monster),
+                    8 => assert_eq!(room, Room::Treasure),
+                    14 => assert_eq!(room, Room::Rest),
+                    _ if y <= 4 => assert!(!matches!(room, Room::Rest | Room::Elite)),
+                    _ => {},
+                }
+                for &(dst_x, dst_y) in map.edges(x, y) {
+                    if y == MAP_HEIGHT - 1 {
+                        assert_eq!((dst_x, dst_y), (BOSS_X, MAP_HEIGHT + 1));
+                    } else {
+                        assert!(dst_y == y + 1 && (dst_x - x).abs() <= 1 && map.room(dst_x, dst_y).is_some());
+                    }
+                }
+            }
+        }
+        // 2本目以降の経路は1本目と違うノードから始まる
+        assert!((0..MAP_WIDTH).filter(|&x| map.room(x, 0).is_some()).count() >= 2);
+        assert!(generate_act_map(1234567890123, 4, 1, &[]).is_none());
+    }
+}
@@ -1,3 +1,6 @@
+use std::collections::VecDeque;
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Mode {
     IsWaiting(Waiting),
@@ -5,26 +8,110 @@ pub enum Mode {
     IsFileIO(FileIO),
 }
 impl Mode {
-    pub fn from(current: &Mode, new: &Mode) -> Mode {
-        match (current, new) {
-            // Waitingからの遷移
-            (Mode::IsWaiting(_), Mode::IsFileIO(_)) => Mode::IsFileIO(FileIO::new()),
-            // Watchingからの遷移
-            (Mode::IsWatching(_), Mode::IsWaiting(_)) => Mode::IsWaiting(Waiting::new()),
-            (Mode::IsWatching(_), Mode::IsFileIO(_)) => Mode::IsFileIO(FileIO::new()),
-            // FileIOからの遷移
-            (Mode::IsFileIO(_), Mode::IsWaiting(_)) => Mode::IsWaiting(Waiting::new()),
-            (Mode::IsFileIO(_), Mode::IsWatching(_)) => Mode::IsWatching(Watching::new()),
-            _ => current.clone(),
+    pub fn kind(&self) -> ModeKind {
+        match self {
+            Mode::IsWaiting(_) => ModeKind::Waiting,
+            Mode::IsWatching(_) => ModeKind::Watching,
+            Mode::IsFileIO(_) => ModeKind::FileIO,
+        }
+    }
+    pub fn from_kind(kind: ModeKind) -> Mode {
+        match kind {
+            ModeKind::Waiting => Mode::IsWaiting(Waiting::new()),
+            ModeKind::Watching => Mode::IsWatching(Watching::new()),
+            ModeKind::FileIO => Mode::IsFileIO(FileIO::new()),
         }
     }
 }
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{:?}", self.kind()) }
+}
+
+// 遷移表で使うモードの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeKind {
+    Waiting,
+    Watching,
+    FileIO,
+}
+
+// モード遷移のきっかけとなるイベント
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    TargetFound,        // 待機中に書き出すべきautosaveを発見
+    TargetChanged,      // 監視中のautosaveが更新された
+    TargetLost,         // autosaveが見つからない(削除された)
+    RepeatLimitReached, // 同じモードの反復回数が上限に達した
+    WriteDone,          // ファイル書き出しが完了した
+    GameStateChanged,   // ゲームの起動/終了を検知した
+}
+
+// 遷移の条件(監視対象の有無)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Guard {
+    Always,
+    HasTarget,
+    NoTarget,
+}
+
+/* モードの遷移表: (遷移元, イベント, 条件, 遷移先)
+
+  表にない組み合わせは不正な遷移としてErrを返す
+*/
+#[rustfmt::skip]
+const TRANSITIONS: &[(ModeKind, Event, Guard, ModeKind)] = &[
+    // Waitingからの遷移
+    (ModeKind::Waiting,  Event::TargetFound,        Guard::Always,    ModeKind::FileIO),
+    (ModeKind::Waiting,  Event::TargetLost,         Guard::Always,    ModeKind::FileIO),
+    (ModeKind::Waiting,  Event::RepeatLimitReached, Guard::Always,    ModeKind::FileIO),
+    // Watchingからの遷移
+    (ModeKind::Watching, Event::TargetChanged,      Guard::Always,    ModeKind::FileIO),
+    (ModeKind::Watching, Event::TargetLost,         Guard::Always,    ModeKind::Waiting),
+    (ModeKind::Watching, Event::RepeatLimitReached, Guard::Always,    ModeKind::Waiting),
+    (ModeKind::Watching, Event::GameStateChanged,   Guard::Always,    ModeKind::Waiting),
+    // FileIOからの遷移
+    (ModeKind::FileIO,   Event::WriteDone,          Guard::HasTarget, ModeKind::Watching),
+    (ModeKind::FileIO,   Event::WriteDone,          Guard::NoTarget,  ModeKind::Waiting),
+];
+
+// 実行されたモード遷移
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub from: Mode,
+    pub event: Event,
+    pub to: Mode,
+}
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "from {} to {} ({:?})", self.from, self.to, self.event)
+    }
+}
+
+// 遷移表にない遷移を要求された場合のエラー
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionError {
+    pub from: Mode,
+    pub event: Event,
+    pub has_target: bool,
+}
+impl fmt::Display for TransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "illegal transition: {:?} in {} (has_target: {})", self.event, self.from, self.has_target)
+    }
+}
+
+// 遷移履歴として保持する件数
+const HISTORY_LEN: usize = 32;
+
+pub type TransitionHook = Box<dyn FnMut(&Transition)>;
 
 pub struct ModeSelector {
     current_mode: Mode,
     has_target: bool,
     do_writing: bool,
     times_repeated: u16,
+    history: VecDeque<Transition>,
+    hook: Option<TransitionHook>,
 }
 #[rustfmt::skip]
 impl ModeSelector {
@@ -34,12 +121,40 @@ impl ModeSelector {
             has_target: false,
             do_writing: true,
             times_repeated: 0_u16,
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            hook: None,
         }
     }
     pub fn current_mode(&self) -> Mode { self.current_mode.clone() }
-    pub fn switch_mode(&mut self, new_mode: &Mode) {
-        self.current_mode = Mode::from(&self.current_mode, new_mode);
+
+    // イベントに応じて遷移表の通りにモードを切り替える
+    pub fn fire(&mut self, event: Event) -> Result<Mode, TransitionError> {
+        let from = self.current_mode.kind();
+        let rule = TRANSITIONS.iter().find(|(rule_from, rule_event, guard, _)| {
+            *rule_from == from && *rule_event == event && match guard {
+                Guard::Always => true,
+                Guard::HasTarget => self.has_target,
+                Guard::NoTarget => !self.has_target,
+            }
+        });
+        let Some((_, _, _, to)) = rule else {
+            return Err(TransitionError { from: self.current_mode(), event, has_target: self.has_target });
+        };
+        let transition = Transition { from: self.current_mode(), event, to: Mode::from_kind(*to) };
+        self.current_mode = transition.to.clone();
+        if let Some(hook) = self.hook.as_mut() {
+            hook(&transition);
+        }
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(transition);
+        Ok(self.current_mode())
     }
+    // 遷移のたびに呼ばれるフック(ログ出力など)
+    pub fn set_hook(&mut self, hook: TransitionHook) { self.hook = Some(hook); }
+    // 直近の遷移履歴(古い順)
+    pub fn history(&self) -> impl Iterator<Item = &Transition> { self.history.iter() }
 
     pub fn has_target(&self) -> bool { self.has_target }
     pub fn found_target(&mut self) { self.has_target = true; }
@@ -71,3 +186,49 @@ pub struct FileIO {}
 impl FileIO {
     pub fn new() -> Self { Self {} }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn write_done_goes_to_watching_only_with_target() {
+        let mut mode_selector = ModeSelector::new();
+        mode_selector.fire(Event::TargetLost).unwrap();
+        assert_eq!(mode_selector.fire(Event::WriteDone), Ok(Mode::IsWaiting(Waiting::new())));
+
+        mode_selector.found_target();
+        mode_selector.fire(Event::TargetFound).unwrap();
+        assert_eq!(mode_selector.fire(Event::WriteDone), Ok(Mode::IsWatching(Watching::new())));
+    }
+
+    #[test]
+    fn illegal_transition_is_rejected_without_changing_mode() {
+        let mut mode_selector = ModeSelector::new();
+        let err = mode_selector.fire(Event::WriteDone).unwrap_err();
+        assert_eq!(err.from, Mode::IsWaiting(Waiting::new()));
+        assert_eq!(mode_selector.current_mode(), Mode::IsWaiting(Waiting::new()));
+        assert_eq!(mode_selector.history().count(), 0);
+    }
+
+    #[test]
+    fn hook_and_history_see_every_transition() {
+        let seen: Rc<RefCell<Vec<String>>> = Rc::default();
+        let mut mode_selector = ModeSelector::new();
+        let hook_seen = Rc::clone(&seen);
+        mode_selector.set_hook(Box::new(move |t| hook_seen.borrow_mut().push(t.to_string())));
+
+        mode_selector.found_target();
+        for _ in 0..HISTORY_LEN {
+            mode_selector.fire(Event::TargetFound).unwrap();
+            mode_selector.fire(Event::WriteDone).unwrap();
+            mode_selector.fire(Event::RepeatLimitReached).unwrap();
+        }
+        assert_eq!(seen.borrow()[0], "from Waiting to FileIO (TargetFound)");
+        assert_eq!(seen.borrow().len(), HISTORY_LEN * 3);
+        assert_eq!(mode_selector.history().count(), HISTORY_LEN);
+        assert_eq!(mode_selector.history().last().unwrap().event, Event::RepeatLimitReached);
+    }
+}
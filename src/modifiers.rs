@@ -0,0 +1,148 @@
+// カスタムモード/デイリーのモディファイアを扱うモジュール
+//
+// autosaveにはモディファイアのID("SealedDeck"など)だけが入るので、表示名に直して出力する
+// 通常のrunと混ざらないように、保存するrunにもrunの種類とモディファイアを記録する
+use libsts::Save;
+use serde_json::{json, Value};
+
+// IDの単語を区切るだけでは表示名にならないモディファイア
+const MOD_NAMES: &[(&str, &str)] = &[
+    ("Allstar", "All Star"),
+];
+
+// runの種類(デイリーはカスタムより優先)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunKind {
+    Standard,
+    Daily,
+    Custom,
+}
+#[rustfmt::skip]
+impl RunKind {
+    pub fn of(save: &Save) -> Self {
+        if save.is_daily {
+            Self::Daily
+        } else if save.is_trial || !save.custom_mods.is_empty() {
+            Self::Custom
+        } else {
+            Self::Standard
+        }
+    }
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Daily => "daily",
+            Self::Custom => "custom",
+        }
+    }
+    // 集計の見出しに添える名前
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Standard => "",
+            Self::Daily => " Daily Climb",
+            Self::Custom => " Custom",
+        }
+    }
+}
+
+// 保存するrunにつけるタグ(モディファイアはIDのまま)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunTag {
+    pub kind: RunKind,
+    pub mods: Vec<String>,
+}
+impl RunTag {
+    pub fn new(save: &Save) -> Self {
+        let kind = RunKind::of(save);
+        let mods = match kind {
+            RunKind::Standard => Vec::new(),
+            RunKind::Daily => save.daily_mods.clone(),
+            RunKind::Custom => save.custom_mods.clone(),
+        };
+        Self { kind, mods }
+    }
+    pub fn mod_names(&self) -> Vec<String> { self.mods.iter().map(|id| mod_name(id)).collect() }
+    pub fn to_json(&self) -> Value {
+        json!({ "kind": self.kind.as_str(), "mods": self.mod_names() })
+    }
+}
+
+// "ControlledChaos" -> "Controlled Chaos", "Sealed Deck" -> "Sealed Deck"
+pub fn mod_name(id: &str) -> String {
+    if let Some((_, name)) = MOD_NAMES.iter().find(|(mod_id, _)| *mod_id == id) {
+        return name.to_string();
+    }
+    let mut name = String::new();
+    for (i, c) in id.chars().enumerate() {
+        let prev = name.chars().last();
+        if i > 0 && c.is_uppercase() && prev.is_some_and(char::is_lowercase) {
+            name.push(' ');
+        }
+        name.push(c);
+    }
+    name
+}
+
+fn join_or_none(names: &[String]) -> String {
+    match names.is_empty() {
+        true => String::from("none"),
+        false => names.join(", "),
+    }
+}
+
+// basic infoに添える行(通常のrunなら空) 例: "Run type: Custom (Sealed Deck, Draft)"
+pub fn compose_run_type_line(tag: &RunTag) -> String {
+    match tag.kind {
+        RunKind::Standard => String::new(),
+        RunKind::Daily => format!("Run type: Daily Climb ({})\n", join_or_none(&tag.mod_names())),
+        RunKind::Custom => format!("Run type: Custom ({})\n", join_or_none(&tag.mod_names())),
+    }
+}
+
+/* sts_modifiers.txtの内容
+
+  Custom run
+  Sealed Deck
+  Draft
+*/
+pub fn compose_txt_modifiers(tag: &RunTag) -> String {
+    let mut ret = match tag.kind {
+        RunKind::Standard => return String::from("Standard run\n"),
+        RunKind::Daily => String::from("Daily Climb\n"),
+        RunKind::Custom => String::from("Custom run\n"),
+    };
+    for name in tag.mod_names() {
+        ret.push_str(&name);
+        ret.push('\n');
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture::*;
+
+    #[test]
+    fn decodes_modifier_ids() {
+        assert_eq!(mod_name("SealedDeck"), "Sealed Deck");
+        assert_eq!(mod_name("ControlledChaos"), "Controlled Chaos");
+        assert_eq!(mod_name("Allstar"), "All Star");
+        assert_eq!(mod_name("Time Dilation"), "Time Dilation");
+        assert_eq!(mod_name("Draft"), "Draft");
+    }
+
+    #[test]
+    fn custom_runs_are_tagged_with_their_modifiers() {
+        let mut save = example_save();
+        let tag = RunTag::new(&save);
+        assert_eq!(tag.kind, RunKind::Standard);
+        assert_eq!((compose_run_type_line(&tag).as_str(), compose_txt_modifiers(&tag).as_str()), ("", "Standard run\n"));
+
+        save.custom_mods = vec![String::from("SealedDeck"), String::from("Draft"), String::from("Inception")];
+        let tag = RunTag::new(&save);
+        assert_eq!(compose_run_type_line(&tag), "Run type: Custom (Sealed Deck, Draft, Inception)\n");
+        assert_eq!(compose_txt_modifiers(&tag), "Custom run\nSealed Deck\nDraft\nInception\n");
+        assert_eq!(tag.to_json()["kind"], "custom");
+    }
+}
@@ -0,0 +1,653 @@
+// autosaveの監視ロジック本体(Waiting/Watching/FileIOの3モード)
+// ファイルシステムと時計はトレイト経由で受け取るので、テストでは偽実装で駆動できる
+use libsts::Save;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::card_choices::*;
+use crate::common::*;
+use crate::daily::*;
+use crate::deck_stats::*;
+use crate::diff::*;
+use crate::economy::*;
+use crate::logger;
+use crate::map::*;
+use crate::mode::*;
+use crate::modifiers::*;
+use crate::preferences::*;
+use crate::process::*;
+use crate::schedule::*;
+use crate::seed::*;
+use crate::shutdown;
+use crate::streak::*;
+use crate::state::*;
+use crate::system::*;
+use crate::timeline::*;
+use crate::timer::*;
+
+const AUTOSAVE_EXTENSION: &str = "autosave";
+
+pub struct Observer<F: FileSystem, C: Clock> {
+    fs: F,
+    clock: C,
+    json_data: JsonData,
+    state_data: StateData,
+    target: Target,
+    mode_selector: ModeSelector,
+    max_mode_repeat: u16,
+    scheduler: Scheduler,
+    process_monitor: ProcessMonitor,
+    run_timer: RunTimer,
+    card_history: CardChoiceHistory,
+    last_save: Option<(String, Save)>, // 差分計算用に直前のSaveを保持
+}
+#[rustfmt::skip]
+impl<F: FileSystem, C: Clock> Observer<F, C> {
+    pub fn new(fs: F, clock: C, json_data: JsonData) -> Self {
+        let state_data = StateData::load(&fs, &json_data);
+        let card_history = CardChoiceHistory::load(&fs, &json_data);
+        let mut target = Target::new();
+        target.init_dir_path(&json_data);
+        target.create_write_txt_dir(&fs);
+        info!("Last observed autosave: {} (modified_time: {})",
+              state_data.character_type(), state_data.modified_time());
+        let mut mode_selector = ModeSelector::new(); // モード管理用マシン
+        mode_selector.set_hook(Box::new(|transition| info!("Mode transition: {}", transition)));
+        Self {
+            max_mode_repeat: json_data.get_value_from_key("max_mode_repeat").unwrap(),
+            scheduler: Scheduler::new(Intervals::from_json(&json_data)),
+            process_monitor: ProcessMonitor::new(json_data.get_value_from_key("detect_game_process").unwrap_or(true)),
+            fs,
+            clock,
+            json_data,
+            state_data,
+            target,
+            mode_selector,
+            run_timer: RunTimer::new(),
+            card_history,
+            last_save: None,
+        }
+    }
+    // getter
+    pub fn current_mode(&self) -> Mode { self.mode_selector.current_mode() }
+
+    // 終了要求があるまで監視を続ける
+    pub fn run(&mut self) {
+        let mut loop_counter: u64 = 0;
+        while !shutdown::requested() {
+            loop_counter += 1;
+            trace!("<<<<Loop{}>>>> current_mode: {}", loop_counter, self.current_mode());
+            self.reload_settings();
+            let delay_ms = self.tick();
+            trace!("Now on interval...({} ms)", delay_ms);
+            self.clock.sleep_ms(delay_ms);
+        }
+    }
+
+    /* 現在のモードに応じた処理を1回行い、次の処理までの待ち時間(ms)を返す
+
+      Waiting: autosaveを探す(autosaveもゲームもなければ間隔を徐々に延ばす)
+      Watching: 監視対象を確認する(最近更新されていれば間隔を縮め、Waitingに戻ったら即座に)
+      FileIO: 変更を検知したらウェイトを挟まずに同じtick内で書き出す
+    */
+    pub fn tick(&mut self) -> u64 {
+        let now_secs = self.clock.now_secs();
+        self.process_monitor.refresh(&self.fs, now_secs);
+        self.target.write_txt_session(&self.fs, &self.process_monitor.session_info(now_secs));
+        let lost_target = match self.current_mode() {
+            Mode::IsWaiting(_) => {
+                self.waiting_step();
+                false
+            },
+            Mode::IsWatching(_) => {
+                self.watching_step();
+                matches!(self.current_mode(), Mode::IsWaiting(_))
+            },
+            Mode::IsFileIO(_) => false,
+        };
+        if let Mode::IsFileIO(_) = self.current_mode() {
+            self.fileio_step();
+        }
+        // タイマーは保存の間も進めるので毎tick書き出す
+        if self.run_timer.has_run() {
+            let game_running = self.process_monitor.running();
+            self.target.write_txt_timer(&self.fs, &self.run_timer, self.clock.now_secs(), game_running);
+        }
+        match self.current_mode() {
+            _ if lost_target => 0,
+            Mode::IsWatching(_) => self.scheduler.watching_delay(&self.activity()),
+            _ => self.scheduler.waiting_delay(&self.activity()),
+        }
+    }
+
+    // 待ち時間の判断材料を集める
+    fn activity(&self) -> Activity {
+        let autosave_present = self.mode_selector.has_target();
+        Activity {
+            now_secs: self.clock.now_secs(),
+            autosave_present,
+            last_change_secs: Some(self.target.modified_time()).filter(|secs| autosave_present && *secs > 0),
+            game_running: self.process_monitor.running(),
+        }
+    }
+
+    /* -----------------------------------
+        待機モードの処理(1回分)
+    ----------------------------------- */
+    pub fn waiting_step(&mut self) {
+        trace!("<<Waiting mode: loop{}>>", self.mode_selector.times_repeated() + 1);
+        // 待機中は毎回すべてのautosaveを確認するので起動/終了の検知は読み捨てる
+        self.process_monitor.take_change();
+
+        /* autosaveの更新日時を比較して状態を更新&モード分岐
+
+          前回より新しい場合: txt出力後に監視モードへ遷移
+          前回と等しい場合: 待機モードを反復(txt出力しない)
+          それ以外: 初回のみ空のtxt出力後に待機モードを反復
+        */
+        self.mode_selector.reset_target();
+        // キャラクターごとのautosaveに加えてデイリーやセーブスロット2以降のautosaveも対象にする
+        for autosave_path in self.autosave_paths() {
+            if !self.fs.is_file(&autosave_path) {
+                continue; // 指定したファイル以外はスキップ
+            }
+            // 前回の更新日時と比較＆監視対象更新＆モード分岐
+            autosave_mode_selector(&self.fs, &mut self.mode_selector, &mut self.target, &mut self.state_data, &autosave_path);
+        }
+
+        if self.mode_selector.has_target() {
+            debug!("Autosave file found: autosave_path: {}, modified_time: {}, character_type: {}",
+                   &self.target.full_path(),
+                   &self.target.modified_time(),
+                   &self.target.character_type());
+        } else {
+            trace!("No '.autosave' file found");
+        }
+
+        // 反復回数上限に達した際パス先が存在しなければ空txt出力
+        let repeat_limit_reached = self.mode_selector.times_repeated() >= self.max_mode_repeat;
+        if repeat_limit_reached && !self.target.autosave_exists(&self.fs) {
+            self.mode_selector.turn_on_do_writing();
+        }
+
+        // ファイルI/O遷移判定
+        let event = if self.mode_selector.has_target() {
+            Event::TargetFound
+        } else if repeat_limit_reached {
+            Event::RepeatLimitReached
+        } else {
+            Event::TargetLost
+        };
+        switch_to_fileio(&mut self.mode_selector, event);
+    }
+
+    // autosaveディレクトリ内の.autosaveファイル(ディレクトリがなければ空)
+    fn autosave_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.fs.read_dir(&self.target.autosave_dir_path())
+                                             .unwrap_or_default()
+                                             .into_iter()
+                                             .filter(|path| path.extension().is_some_and(|ext| ext == AUTOSAVE_EXTENSION))
+                                             .collect();
+        paths.sort();
+        paths
+    }
+
+    /* -----------------------------------
+        監視モードの処理(1回分)
+    ----------------------------------- */
+    pub fn watching_step(&mut self) {
+        trace!("<<Watching mode: loop{}>>", self.mode_selector.times_repeated() + 1);
+        // ゲームの起動/終了を検知したらすぐに待機(Waiting)モードへ遷移して他のautosaveファイルを確認
+        if self.process_monitor.running().is_some() && self.process_monitor.take_change() {
+            debug!("Game state changed, shift to Waiting mode");
+            switch_to_waiting(&mut self.mode_selector, Event::GameStateChanged);
+            return;
+        }
+        if self.mode_selector.times_repeated() >= self.max_mode_repeat {
+            // ゲームの起動中も定期的にループから抜け出して他のautosaveファイルを確認(別キャラクターのrunの開始など)
+            debug!("Periodic shift to Waiting mode");
+            switch_to_waiting(&mut self.mode_selector, Event::RepeatLimitReached);
+            return;
+        }
+        // 毎ループ監視対象のautosaveファイルの存在を確認
+        if self.target.autosave_exists(&self.fs) {
+            trace!("{}'s autosave exists", &self.target.character_type());
+            self.mode_selector.found_target(); // 一応
+        } else {
+            // autosaveが削除されていれば再び待機モードへ
+            info!("{}'s autosave does not exist", &self.target.character_type());
+            self.run_timer.stop(self.clock.now_secs()); // runの終了とみなしてタイマーを止める
+            switch_to_waiting(&mut self.mode_selector, Event::TargetLost);
+            return;
+        }
+
+        // 監視中autosaveの更新日時比較＆監視対象更新＆モード分岐
+        let cloned_full_path = self.target.full_path();
+        let target_path = Path::new(&cloned_full_path);
+        autosave_mode_selector(&self.fs, &mut self.mode_selector, &mut self.target, &mut self.state_data, target_path);
+
+        // ファイルI/O遷移判定
+        switch_to_fileio(&mut self.mode_selector, Event::TargetChanged);
+    }
+
+    /* -----------------------------------
+        ファイルI/Oモードの処理
+    ----------------------------------- */
+    pub fn fileio_step(&mut self) {
+        trace!("<<FileI/O mode>>");
+        // 設定ファイルはランの終了時に更新されるので、監視対象の有無に関わらず読み直す
+        let prefs = load_preferences(&self.fs, &preferences_dir_path(&self.json_data));
+        if let Some(prefs) = &prefs {
+            self.target.write_txt_progress(&self.fs, prefs);
+        }
+        // 連勝数はstreakサブコマンドで修正されることがあるので毎回読み直す
+        let streak_rules = StreakRules::from_json(&self.json_data);
+        let mut streak_tracker = StreakTracker::load(&self.fs, &self.json_data);
+        streak_tracker.update_from_runs(&self.fs, &runs_dir_path(&self.json_data), &streak_rules);
+        self.target.write_txt_streak(&self.fs, &compose_txt_streak(&streak_tracker, &streak_rules));
+        self.target.merge_write_stats(streak_tracker.take_write_stats());
+        // 監視対象の有無で書き出すファイル内容を場合分け
+        if self.mode_selector.has_target() {
+            // 監視対象のファイルを読み込んでtxt書き出し(targetありautosaveの更新差分あり)
+            match load_save_with_raw(&self.fs, self.target.full_path()) {
+                Ok((save, raw)) => self.write_txt_all(save, &raw, prefs.as_ref()),
+                Err(e) => error!("Failed to read the autosave, skipping this update: {}", e),
+            }
+        } else {
+            // 書き出し(targetなし)
+            self.target.write_txt_empty(&self.fs);
+            self.mode_selector.turn_off_do_writing(); // 次に更新差分を検知するまで書き出しOFF
+        }
+        // 監視対象があれば監視(Watching)モード、なければ待機(Waiting)モードへ遷移
+        fire_event(&mut self.mode_selector, Event::WriteDone);
+        self.collect_write_stats();
+        let stats = self.target.write_stats();
+        debug!("Write stats: {} written, {} unchanged, {} failed",
+               stats.written(), stats.unchanged(), stats.failed());
+    }
+
+    fn write_txt_all(&mut self, save: Save, raw: &Value, prefs: Option<&Preferences>) {
+        self.process_monitor.record_run(save.seed);
+        self.run_timer.update(&save, self.clock.now_secs());
+        self.target.write_txt_session(&self.fs, &self.process_monitor.session_info(self.clock.now_secs()));
+        // デイリーやセーブスロットのautosaveはファイル名がキャラクター名とは限らない
+        let character = character_of(&self.target.character_type(), &save);
+        let progress = prefs.and_then(|prefs| prefs.character(&character));
+        self.target.write_txt_basic_info(&self.fs, &save, progress);
+        self.target.write_txt_enemies(&self.fs, &save);
+        self.target.write_txt_events(&self.fs, &save);
+        self.target.write_txt_economy(&self.fs, &Economy::new(&save));
+        self.target.write_txt_boss(&self.fs, &save);
+        self.target.write_txt_deck_stats(&self.fs, &DeckStats::new(&save));
+        let run_seed = RunSeed::new(&save, raw);
+        self.target.write_txt_seed(&self.fs, &run_seed);
+        let map_path = MapPath::new(&save);
+        self.target.write_txt_map(&self.fs, &save, &map_path);
+        let daily = DailyRun::new(&save);
+        self.target.write_txt_daily(&self.fs, daily.as_ref());
+        let run_tag = RunTag::new(&save);
+        self.target.write_txt_modifiers(&self.fs, &run_tag);
+        let choices = choices_of(&save);
+        self.card_history.record_run(&self.fs, &run_seed.seed, &character, &run_tag, &choices);
+        self.target.write_txt_card_choices(&self.fs, &choices, card_choices_shown(&self.json_data));
+        let choices_json = compose_json_card_choices(&run_seed.seed, &character, &choices);
+        self.target.write_json_card_choices(&self.fs, &choices_json);
+        self.target.write_txt_pick_rates(&self.fs, &self.card_history, &character, run_tag.kind);
+        self.target.write_txt_timeline(&self.fs, &RunTimeline::new(&save), save.act_num);
+        self.target.write_json_run(&self.fs, &compose_json_run(&save, &run_seed, &map_path));
+        // 同じautosaveの直前のSaveがあれば差分を書き出し
+        match &self.last_save {
+            Some((path, prev_save)) if *path == self.target.full_path() => {
+                self.target.write_txt_last_change(&self.fs, &SaveDiff::new(prev_save, &save));
+            },
+            _ => self.target.write_txt_last_change(&self.fs, &SaveDiff::default()),
+        }
+        // self.target.write_autosave(&self.fs, &save);
+        // self.target.write_autosave_beta(&self.fs, &save);
+        self.last_save = Some((self.target.full_path(), save));
+    }
+
+    // settings.jsonが変更されていれば再読み込みして反映(無効な内容なら以前の設定を維持)
+    pub fn reload_settings(&mut self) {
+        match self.json_data.reload_if_modified(&self.fs) {
+            Ok(true) => {
+                self.target.init_dir_path(&self.json_data);
+                self.target.create_write_txt_dir(&self.fs);
+                self.max_mode_repeat = self.json_data.get_value_from_key("max_mode_repeat").unwrap();
+                self.scheduler.set_intervals(Intervals::from_json(&self.json_data));
+                self.process_monitor.set_enabled(self.json_data.get_value_from_key("detect_game_process").unwrap_or(true));
+                if let Err(e) = logger::configure(&self.json_data) {
+                    warn!("Invalid log settings, keeping previous ones: {}", e);
+                }
+                info!("Settings reloaded: {}", self.json_data.path().display());
+            },
+            Ok(false) => {},
+            Err(e) => error!("Settings reload rejected, keeping previous settings: {}", e),
+        }
+    }
+
+    // 状態ファイルとカード選択履歴の書き出し結果をtxtと同じカウンタに集める
+    fn collect_write_stats(&mut self) {
+        self.target.merge_write_stats(self.state_data.take_write_stats());
+        self.target.merge_write_stats(self.card_history.take_write_stats());
+    }
+
+    // 終了処理: 空txtの書き出し(任意)と状態の保存、実行中も含めてすべての書き出しが成功すればtrue
+    pub fn shutdown(&mut self) -> bool {
+        info!("Shutdown requested, flushing outputs and state");
+        for transition in self.mode_selector.history() {
+            debug!("Recent transition: {}", transition);
+        }
+        // オーバーレイに古い内容が残らないよう空txtを書き出す
+        if self.json_data.get_value_from_key("write_empty_on_exit").unwrap_or(false) {
+            self.target.write_txt_empty(&self.fs);
+            self.target.write_txt_empty_live(&self.fs);
+        }
+        self.state_data.save(&self.fs);
+        self.collect_write_stats();
+        let failed = self.target.write_stats().failed();
+        if failed > 0 {
+            error!("{} writes failed during this session", failed);
+        }
+        failed == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fake::*;
+    use crate::system::fixture::*;
+    use serde_json::json;
+
+    const SAVES: &str = "/saves/";
+    const OUT: &str = "/out/";
+    const NOW: u64 = 10000;
+    const CARD_CHOICE: &str = r#""metric_card_choices": [{"floor": 1, "picked": "Anger", "not_picked": ["Clash"]}]"#;
+
+    fn settings() -> Value {
+        json!({
+            "autosave_dir_path": SAVES,
+            "write_txt_dir_path": OUT,
+            "data_dir_path": "/data/",
+            "waiting_interval_ms": 3000,
+            "watching_interval_ms": 1000,
+            "max_mode_repeat": 3,
+        })
+    }
+    fn observer(fs: MemFileSystem) -> Observer<MemFileSystem, FakeClock> {
+        Observer::new(fs, FakeClock::at(NOW), JsonData::from_value(settings()))
+    }
+    fn autosave_path() -> String { format!("{}IRONCLAD.autosave", SAVES) }
+    fn out(observer: &Observer<MemFileSystem, FakeClock>, file_name: &str) -> Option<String> {
+        observer.fs.contents(format!("{}{}", OUT, file_name))
+    }
+
+    #[test]
+    fn no_autosave_writes_empty_txt_once() {
+        let mut observer = observer(MemFileSystem::default());
+        observer.waiting_step();
+        assert!(matches!(observer.current_mode(), Mode::IsFileIO(_)));
+        observer.fileio_step();
+        assert!(matches!(observer.current_mode(), Mode::IsWaiting(_)));
+        assert_eq!(out(&observer, "sts_basic_info.txt").as_deref(), Some("---\n"));
+
+        observer.waiting_step();
+        assert!(matches!(observer.current_mode(), Mode::IsWaiting(_)));
+    }
+
+    #[test]
+    fn newer_autosave_while_waiting_is_written_and_watched() {
+        let mut observer = observer(MemFileSystem::default());
+        observer.waiting_step();
+        observer.fileio_step();
+        observer.waiting_step();
+        assert!(matches!(observer.current_mode(), Mode::IsWaiting(_)));
+
+        // 待機中にautosaveが現れたらFileIOへ
+        observer.fs.set_file(autosave_path(), AUTOSAVE, 100);
+        observer.waiting_step();
+        assert!(matches!(observer.current_mode(), Mode::IsFileIO(_)));
+        observer.fileio_step();
+        assert!(matches!(observer.current_mode(), Mode::IsWatching(_)));
+        assert!(out(&observer, "sts_basic_info.txt").unwrap().starts_with("Player's Name: YOUR NAME\n"));
+        assert_eq!(observer.state_data.modified_time(), 100);
+
+        // 更新がなければ監視を続け、更新されれば再びFileIOへ
+        observer.watching_step();
+        assert!(matches!(observer.current_mode(), Mode::IsWatching(_)));
+        observer.fs.set_file(autosave_path(), &AUTOSAVE.replace("\"gold\": 110", "\"gold\": 65"), 101);
+        observer.watching_step();
+        assert!(matches!(observer.current_mode(), Mode::IsFileIO(_)));
+        observer.fileio_step();
+        assert_eq!(out(&observer, "sts_last_change.txt").as_deref(), Some("-45 gold\n"));
+    }
+
+    #[test]
+    fn preferences_add_progress_to_ascension_line() {
+        let fs = MemFileSystem::default();
+        fs.set_file(autosave_path(), AUTOSAVE, 100);
+        fs.set_file("/preferences/STSDataVagabond", r#"{"ASCENSION_LEVEL": "5", "WIN_STREAK": "1", "WIN_COUNT": "3", "LOSE_COUNT": "7"}"#, 0);
+        let mut observer = observer(fs);
+        observer.waiting_step();
+        observer.fileio_step();
+        let basic_info = out(&observer, "sts_basic_info.txt").unwrap();
+        assert!(basic_info.contains(" (highest: 5, streak: 1, wins/losses: 3/7)\n"), "{}", basic_info);
+        assert!(out(&observer, "sts_progress.txt").unwrap().contains("THE_SILENT: locked\n"));
+    }
+
+    #[test]
+    fn daily_autosave_is_found_and_kept_apart() {
+        let fs = MemFileSystem::default();
+        let daily = AUTOSAVE.replace("\"is_daily\": false", "\"is_daily\": true")
+                            .replace("\"daily_date\": 0", "\"daily_date\": 19787")
+                            .replace("\"daily_mods\": []", "\"daily_mods\": [\"Binary\"]")
+                            .replace("\"metric_card_choices\": []", CARD_CHOICE);
+        fs.set_file(format!("{}DAILY.autosave", SAVES), &daily, 100);
+        fs.set_file("/preferences/STSDataVagabond", r#"{"ASCENSION_LEVEL": "5"}"#, 0);
+        let mut observer = observer(fs);
+        observer.waiting_step();
+        observer.fileio_step();
+        assert!(matches!(observer.current_mode(), Mode::IsWatching(_)));
+        assert_eq!(out(&observer, "sts_daily.txt").as_deref(), Some("Daily Climb: 2024-03-05\nModifiers: Binary\n"));
+        // ファイル名ではなくautosaveの中身からキャラクターを判断して、通常のrunとは分けて集計する
+        assert_eq!(observer.card_history.runs_of("IRONCLAD", RunKind::Daily), 1);
+        assert_eq!(observer.card_history.runs_of("IRONCLAD", RunKind::Standard), 0);
+        assert_eq!(out(&observer, "sts_card_pick_rates.txt").as_deref(),
+                   Some("IRONCLAD Daily Climb pick rates (1 runs)\nAnger: 1/1 (100%)\nClash: 0/1 (0%)\n"));
+        assert!(out(&observer, "sts_basic_info.txt").unwrap().contains("Ascension level: 1 (highest: 5, "));
+    }
+
+    #[test]
+    fn slot_autosave_is_keyed_by_character() {
+        let fs = MemFileSystem::default();
+        fs.set_file(format!("{}1_IRONCLAD.autosave", SAVES), &AUTOSAVE.replace("\"metric_card_choices\": []", CARD_CHOICE), 100);
+        fs.set_file("/preferences/STSDataVagabond", r#"{"ASCENSION_LEVEL": "5"}"#, 0);
+        let mut observer = observer(fs);
+        observer.waiting_step();
+        observer.fileio_step();
+        assert_eq!(observer.card_history.runs_of("IRONCLAD", RunKind::Standard), 1);
+        assert!(out(&observer, "sts_card_pick_rates.txt").unwrap().starts_with("IRONCLAD pick rates (1 runs)\n"));
+        assert!(out(&observer, "sts_basic_info.txt").unwrap().contains("Ascension level: 1 (highest: 5, "));
+    }
+
+    #[test]
+    fn autosave_deleted_while_watching_returns_to_waiting() {
+        let fs = MemFileSystem::default();
+        fs.set_file(autosave_path(), AUTOSAVE, 100);
+        let mut observer = observer(fs);
+        observer.waiting_step();
+        observer.fileio_step();
+        assert!(matches!(observer.current_mode(), Mode::IsWatching(_)));
+
+        observer.fs.remove_file(autosave_path());
+        observer.watching_step();
+        assert!(matches!(observer.current_mode(), Mode::IsWaiting(_)));
+        observer.waiting_step();
+        observer.fileio_step();
+        assert_eq!(out(&observer, "sts_basic_info.txt").as_deref(), Some("---\n"));
+    }
+
+    #[test]
+    fn same_autosave_after_restart_is_not_rewritten() {
+        let fs = MemFileSystem::default();
+        fs.set_file(autosave_path(), AUTOSAVE, 100);
+        fs.set_file("/data/state.json", r#"{"modified_time": 100, "character_type": "IRONCLAD"}"#, 0);
+        let mut observer = observer(fs);
+        observer.waiting_step();
+        assert!(matches!(observer.current_mode(), Mode::IsWaiting(_)));
+        assert!(out(&observer, "sts_basic_info.txt").is_none());
+    }
+
+    #[test]
+    fn data_file_failures_reach_the_write_stats_and_exit_status() {
+        let fs = MemFileSystem::default();
+        fs.set_file(autosave_path(), &AUTOSAVE.replace("\"metric_card_choices\": []", CARD_CHOICE), 100);
+        fs.set_read_only("/data/");
+        let mut observer = observer(fs);
+        observer.waiting_step();
+        observer.fileio_step();
+        assert!(out(&observer, "sts_basic_info.txt").is_some());
+        // 状態ファイル・連勝数・カード選択履歴の3つ
+        assert_eq!(observer.target.write_stats().failed(), 3);
+        assert!(!observer.shutdown());
+    }
+
+    #[test]
+    fn clean_session_shuts_down_successfully() {
+        let fs = MemFileSystem::default();
+        fs.set_file(autosave_path(), AUTOSAVE, 100);
+        let mut observer = observer(fs);
+        observer.tick();
+        assert!(observer.shutdown());
+    }
+
+    #[test]
+    fn tick_writes_immediately_and_uses_mode_intervals() {
+        let mut observer = observer(MemFileSystem::default());
+        // autosaveなし: 空txtを同じtick内で書き出して待機中の間隔
+        assert_eq!(observer.tick(), 3000);
+        assert_eq!(out(&observer, "sts_basic_info.txt").as_deref(), Some("---\n"));
+        assert_eq!(observer.tick(), 6000);
+
+        // 新しいautosave: 同じtick内で書き出して監視中の間隔
+        observer.fs.set_file(autosave_path(), AUTOSAVE, 100);
+        assert_eq!(observer.tick(), 1000);
+        assert!(matches!(observer.current_mode(), Mode::IsWatching(_)));
+        assert!(out(&observer, "sts_basic_info.txt").unwrap().starts_with("Player's Name"));
+        assert_eq!(observer.tick(), 1000);
+
+        // 削除されたら即座にWaitingのチェックへ
+        observer.fs.remove_file(autosave_path());
+        assert_eq!(observer.tick(), 0);
+        assert_eq!(observer.tick(), 3000);
+        assert_eq!(out(&observer, "sts_basic_info.txt").as_deref(), Some("---\n"));
+    }
+
+    #[test]
+    fn timer_ticks_between_saves_and_stops_when_run_ends() {
+        let fs = MemFileSystem::default();
+        fs.set_file(autosave_path(), AUTOSAVE, 100);
+        let mut observer = observer(fs);
+        observer.tick();
+        assert!(out(&observer, "sts_basic_info.txt").unwrap().contains("Play time: 00:00:10\n"));
+        assert_eq!(out(&observer, "sts_seed.txt").as_deref(), Some("Seed: J6KSGCJ8\n"));
+        let run_json: Value = serde_json::from_str(&out(&observer, "sts_run.json").unwrap()).unwrap();
+        assert_eq!(run_json["seed"], json!({ "seed": "J6KSGCJ8", "seeded": false, "daily": false, "custom": false }));
+        assert_eq!(out(&observer, "sts_timer.txt").as_deref(), Some("Run time: 00:00:10\nFloor 0: 00:00:00\n"));
+
+        observer.clock.sleep_ms(5000);
+        observer.tick();
+        assert_eq!(out(&observer, "sts_timer.txt").as_deref(), Some("Run time: 00:00:15\nFloor 0: 00:00:05\n"));
+
+        observer.fs.remove_file(autosave_path());
+        observer.clock.sleep_ms(1000);
+        observer.tick();
+        observer.clock.sleep_ms(60000);
+        observer.tick();
+        assert_eq!(out(&observer, "sts_timer.txt").as_deref(), Some("Run time: 00:00:16 (ended)\n"));
+    }
+
+    #[test]
+    fn timer_freezes_without_process_detection_when_autosave_goes_idle() {
+        let fs = MemFileSystem::default();
+        fs.set_file(autosave_path(), AUTOSAVE, 100);
+        let mut settings = settings();
+        settings["detect_game_process"] = json!(false);
+        let mut observer = Observer::new(fs, FakeClock::at(NOW), JsonData::from_value(settings));
+        observer.tick();
+        observer.clock.sleep_ms(5000);
+        observer.tick();
+        assert_eq!(out(&observer, "sts_timer.txt").as_deref(), Some("Run time: 00:00:15\nFloor 0: 00:00:05\n"));
+
+        // ゲームを閉じてautosaveが更新されなくなったら、上限の時間で止まる
+        observer.clock.sleep_ms(3_600_000);
+        observer.tick();
+        let frozen = out(&observer, "sts_timer.txt");
+        assert_eq!(frozen.as_deref(), Some("Run time: 00:10:10\nFloor 0: 00:10:00\n"));
+        observer.clock.sleep_ms(60000);
+        observer.tick();
+        assert_eq!(out(&observer, "sts_timer.txt"), frozen);
+    }
+
+    #[test]
+    fn polling_adapts_to_recent_changes_and_game_process() {
+        let fs = MemFileSystem::default();
+        fs.set_file("/proc/1/cmdline", "/sbin/init\0", 0);
+        let mut observer = observer(fs);
+        // ゲームもautosaveもなければバックオフ、ゲームが起動したら速めに
+        assert_eq!(observer.tick(), 3000);
+        assert_eq!(observer.tick(), 6000);
+        observer.fs.set_file("/proc/4242/cmdline", "java\0-jar\0desktop-1.0.jar\0", 0);
+        observer.clock.sleep_ms(6000);
+        assert_eq!(observer.tick(), 1000);
+
+        // 直前に更新されたautosaveは短い間隔で監視
+        observer.fs.set_file(autosave_path(), AUTOSAVE, observer.clock.now_secs());
+        assert_eq!(observer.tick(), 500);
+        observer.clock.set_secs(NOW + 600);
+        assert_eq!(observer.tick(), 1000);
+
+        // ゲームが終了したら待機モードに戻り、autosaveが残っていても控えめに
+        observer.fs.remove_file("/proc/4242/cmdline");
+        observer.clock.sleep_ms(6000);
+        assert_eq!(observer.tick(), 0);
+        assert!(matches!(observer.current_mode(), Mode::IsWaiting(_)));
+        assert_eq!(observer.tick(), 3000);
+    }
+
+    #[test]
+    fn game_process_counts_runs_in_the_session() {
+        let fs = MemFileSystem::default();
+        fs.set_file("/proc/4242/cmdline", "java\0-jar\0desktop-1.0.jar\0", 0);
+        fs.set_file(autosave_path(), AUTOSAVE, 100);
+        let mut observer = observer(fs);
+        observer.tick();
+        assert!(matches!(observer.current_mode(), Mode::IsWatching(_)));
+        assert_eq!(out(&observer, "sts_session.txt").as_deref(), Some("Game: running\nSession: 0:00\nRuns this session: 1\n"));
+
+        // 別のシードのrunを数える
+        observer.clock.sleep_ms(60000);
+        observer.fs.set_file(autosave_path(), &AUTOSAVE.replace("1234567890123", "42"), 200);
+        observer.tick();
+        assert_eq!(out(&observer, "sts_session.txt").as_deref(), Some("Game: running\nSession: 0:01\nRuns this session: 2\n"));
+    }
+
+    #[test]
+    fn newer_autosave_of_another_character_is_found_while_the_game_runs() {
+        let fs = MemFileSystem::default();
+        fs.set_file("/proc/4242/cmdline", "java\0-jar\0desktop-1.0.jar\0", 0);
+        fs.set_file(autosave_path(), AUTOSAVE, 100);
+        let mut observer = observer(fs);
+        observer.tick();
+        assert!(matches!(observer.current_mode(), Mode::IsWatching(_)));
+
+        // IRONCLADのautosaveを残したまま別のキャラクターのrunを始めても、定期的な確認で見つける
+        observer.fs.set_file(format!("{}THE_SILENT.autosave", SAVES), &AUTOSAVE.replace("\"gold\": 110", "\"gold\": 99"), 200);
+        for _ in 0..10 {
+            observer.tick();
+        }
+        assert_eq!(observer.target.character_type(), "THE_SILENT");
+        assert!(out(&observer, "sts_basic_info.txt").unwrap().contains("Gold: 99\n"));
+    }
+}
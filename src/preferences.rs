@@ -0,0 +1,164 @@
+// ゲームの設定ファイル(preferencesディレクトリ)からキャラクターごとの進行状況を読むモジュール
+//
+// STSDataVagabondなどはキーも値も文字列のJSON(例: "ASCENSION_LEVEL": "5")
+// STSUnlocksのキャラクター名の値は0: 未解放, 1: 見かけたが未解放, 2: 解放済み
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+use crate::common::*;
+use crate::system::*;
+
+const PREFERENCES_DIR_NAME: &str = "preferences";
+const PLAYER_FILE_NAME: &str = "STSPlayer";
+const UNLOCKS_FILE_NAME: &str = "STSUnlocks";
+
+const UNLOCKED: u32 = 2;
+
+// (autosaveのキャラクター名, 設定ファイル名, STSUnlocksのキー(アイアンクラッドは最初から解放済み))
+const CHARACTER_PREFS: [(&str, &str, Option<&str>); 4] = [
+    ("IRONCLAD", "STSDataVagabond", None),
+    ("THE_SILENT", "STSDataTheSilent", Some("The Silent")),
+    ("DEFECT", "STSDataDefect", Some("Defect")),
+    ("WATCHER", "STSDataWatcher", Some("Watcher")),
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CharacterProgress {
+    pub character: String,
+    pub unlocked: bool,
+    pub highest_ascension: u32,
+    pub win_streak: u32,
+    pub best_win_streak: u32,
+    pub wins: u32,
+    pub losses: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Preferences {
+    pub player_name: Option<String>,
+    pub characters: Vec<CharacterProgress>,
+}
+impl Preferences {
+    // autosaveのファイル名("THE_SILENT"など、空白区切りも可)からキャラクターを探す
+    pub fn character(&self, character_type: &str) -> Option<&CharacterProgress> {
+        let character_type = character_type.replace(' ', "_");
+        self.characters.iter().find(|progress| progress.character == character_type)
+    }
+}
+
+// preferencesディレクトリ(未指定ならautosaveディレクトリと同じ階層)
+pub fn preferences_dir_path(json_data: &JsonData) -> PathBuf {
+    game_dir_path(json_data, "preferences_dir_path", PREFERENCES_DIR_NAME)
+}
+
+// 読める設定ファイルが1つもなければNone
+pub fn load_preferences(fs: &impl FileSystem, dir_path: &Path) -> Option<Preferences> {
+    let read = |file_name: &str| -> Option<Map<String, Value>> {
+        let path = dir_path.join(file_name);
+        let content = fs.read_to_string(&path).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(Value::Object(map)) => Some(map),
+            _ => {
+                warn!("Ignoring unreadable preferences file: {}", path.display());
+                None
+            },
+        }
+    };
+    let player = read(PLAYER_FILE_NAME);
+    let unlocks = read(UNLOCKS_FILE_NAME);
+    let mut found = player.is_some() || unlocks.is_some();
+    let characters = CHARACTER_PREFS.iter()
+        .map(|(character, file_name, unlock_key)| {
+            let prefs = read(file_name).unwrap_or_default();
+            found |= !prefs.is_empty();
+            let unlocked = match unlock_key {
+                None => true,
+                Some(key) => unlocks.as_ref().and_then(|unlocks| pref_u32(unlocks, key)).is_some_and(|n| n == UNLOCKED),
+            };
+            CharacterProgress {
+                character: character.to_string(),
+                unlocked,
+                highest_ascension: pref_u32(&prefs, "ASCENSION_LEVEL").unwrap_or(0),
+                win_streak: pref_u32(&prefs, "WIN_STREAK").unwrap_or(0),
+                best_win_streak: pref_u32(&prefs, "BEST_WIN_STREAK").unwrap_or(0),
+                wins: pref_u32(&prefs, "WIN_COUNT").unwrap_or(0),
+                losses: pref_u32(&prefs, "LOSE_COUNT").unwrap_or(0),
+            }
+        })
+        .collect();
+    let player_name = player.as_ref()
+                            .and_then(|player| player.get("name"))
+                            .and_then(Value::as_str)
+                            .map(String::from);
+    found.then_some(Preferences { player_name, characters })
+}
+
+// 値は文字列で保存されている(念のため数値も受け付ける)
+fn pref_u32(prefs: &Map<String, Value>, key: &str) -> Option<u32> {
+    match prefs.get(key)? {
+        Value::String(text) => text.trim().parse().ok(),
+        value => value.as_u64().and_then(|n| u32::try_from(n).ok()),
+    }
+}
+
+// basic infoのAscension levelの行に添える 例: " (highest: 5, streak: 2, wins/losses: 5/10)"
+pub fn compose_ascension_suffix(progress: &CharacterProgress) -> String {
+    format!(" (highest: {}, streak: {}, wins/losses: {}/{})",
+            progress.highest_ascension, progress.win_streak, progress.wins, progress.losses)
+}
+
+/* sts_progress.txtの内容
+
+  IRONCLAD: A5, streak 2 (best 3), 5 wins / 10 losses
+  THE_SILENT: locked
+*/
+pub fn compose_txt_progress(prefs: &Preferences) -> String {
+    let mut ret = String::new();
+    if let Some(name) = &prefs.player_name {
+        ret.push_str(&format!("Player: {}\n", name));
+    }
+    for progress in &prefs.characters {
+        if !progress.unlocked {
+            ret.push_str(&format!("{}: locked\n", progress.character));
+            continue;
+        }
+        ret.push_str(&format!("{}: A{}, streak {} (best {}), {} wins / {} losses\n",
+                              progress.character, progress.highest_ascension, progress.win_streak,
+                              progress.best_win_streak, progress.wins, progress.losses));
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fake::*;
+    use serde_json::json;
+
+    #[test]
+    fn default_dir_is_next_to_saves() {
+        let json_data = JsonData::from_value(json!({ "autosave_dir_path": "/games/SlayTheSpire/saves/" }));
+        assert_eq!(preferences_dir_path(&json_data), PathBuf::from("/games/SlayTheSpire/preferences"));
+    }
+
+    #[test]
+    fn reads_progress_per_character() {
+        let fs = MemFileSystem::default();
+        assert_eq!(load_preferences(&fs, Path::new("/prefs")), None);
+
+        fs.set_file("/prefs/STSPlayer", r#"{"name": "YOUR NAME"}"#, 0);
+        fs.set_file("/prefs/STSUnlocks", r#"{"The Silent": "2", "Defect": "1"}"#, 0);
+        fs.set_file("/prefs/STSDataVagabond",
+                    r#"{"ASCENSION_LEVEL": "5", "WIN_STREAK": "2", "BEST_WIN_STREAK": "3", "WIN_COUNT": "5", "LOSE_COUNT": "10"}"#, 0);
+        let prefs = load_preferences(&fs, Path::new("/prefs")).unwrap();
+        let ironclad = prefs.character("IRONCLAD").unwrap();
+        assert_eq!(compose_ascension_suffix(ironclad), " (highest: 5, streak: 2, wins/losses: 5/10)");
+        assert!(prefs.character("THE SILENT").unwrap().unlocked);
+        assert_eq!(compose_txt_progress(&prefs),
+                   "Player: YOUR NAME\n\
+                    IRONCLAD: A5, streak 2 (best 3), 5 wins / 10 losses\n\
+                    THE_SILENT: A0, streak 0 (best 0), 0 wins / 0 losses\n\
+                    DEFECT: locked\n\
+                    WATCHER: locked\n");
+    }
+}
@@ -0,0 +1,215 @@
+// ゲーム(Slay the Spire)のプロセスを監視するモジュール
+// /procを走査してコマンドラインを調べる(/procがない環境では検出不可としてNoneを返す)
+// ゲームの起動から終了までを1セッションとして、経過時間とプレイしたrun数を数える
+use std::path::Path;
+
+use crate::system::*;
+
+const PROC_DIR_PATH: &str = "/proc";
+const DEFAULT_SCAN_INTERVAL_SECS: u64 = 5;
+
+// Javaの引数にこれらが含まれていればゲームのプロセスとみなす
+const GAME_MARKERS: [&str; 4] = ["desktop-1.0.jar", "SlayTheSpire", "ModTheSpire", "mts-launcher"];
+// Proton(Wine)経由で起動した場合のランチャー
+const PROTON_LAUNCHER: &str = "slaythespire.exe";
+
+// /procを走査してゲームが起動中か調べる
+pub fn scan_game_running(fs: &impl FileSystem) -> Option<bool> {
+    let entries = fs.read_dir(Path::new(PROC_DIR_PATH)).ok()?;
+    let running = entries.iter()
+                         .filter(|entry| entry.file_name()
+                                              .and_then(|name| name.to_str())
+                                              .is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit())))
+                         .filter_map(|entry| fs.read_to_string(&entry.join("cmdline")).ok())
+                         .any(|cmdline| is_game_cmdline(&cmdline));
+    Some(running)
+}
+
+/* cmdlineは引数がNUL文字区切り
+
+  ネイティブ: .../jre/bin/java -jar desktop-1.0.jar
+  Proton: Z:\...\SlayTheSpire.exe や C:\...\jre\bin\javaw.exe -jar desktop-1.0.jar
+*/
+fn is_game_cmdline(cmdline: &str) -> bool {
+    let mut args = cmdline.split('\0').filter(|arg| !arg.is_empty());
+    let Some(program) = args.next() else {
+        return false;
+    };
+    let program = program.to_ascii_lowercase();
+    if program.ends_with(PROTON_LAUNCHER) {
+        return true;
+    }
+    program.contains("java") && args.any(|arg| GAME_MARKERS.iter().any(|marker| arg.contains(marker)))
+}
+
+// 出力用のセッション情報
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionInfo {
+    pub game_running: Option<bool>,
+    pub session_duration_secs: Option<u64>, // 起動中なら現在の、終了後なら直前のセッションの長さ
+    pub runs_this_session: u32,
+}
+
+// 一定間隔で/procを走査してゲームの起動状態とセッションを保持する
+#[derive(Debug, Clone)]
+pub struct ProcessMonitor {
+    enabled: bool,
+    scan_interval_secs: u64,
+    last_scan_secs: Option<u64>,
+    running: Option<bool>,
+    changed: bool,                     // 前回take_change()以降に起動/終了を検知したか
+    session_started_secs: Option<u64>, // 起動を検知した時刻(実際の起動時刻ではない)
+    last_session_secs: Option<u64>,    // 直前のセッションの長さ
+    runs_this_session: u32,
+    last_run_seed: Option<i64>,
+}
+#[rustfmt::skip]
+impl ProcessMonitor {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            scan_interval_secs: DEFAULT_SCAN_INTERVAL_SECS,
+            last_scan_secs: None,
+            running: None,
+            changed: false,
+            session_started_secs: None,
+            last_session_secs: None,
+            runs_this_session: 0,
+            last_run_seed: None,
+        }
+    }
+    pub fn running(&self) -> Option<bool> { self.running }
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled != self.enabled {
+            self.last_scan_secs = None;
+        }
+        self.enabled = enabled;
+    }
+    // 起動/終了を検知していればtrueを返してフラグを下ろす
+    pub fn take_change(&mut self) -> bool { std::mem::take(&mut self.changed) }
+
+    pub fn refresh(&mut self, fs: &impl FileSystem, now_secs: u64) -> Option<bool> {
+        if !self.enabled {
+            self.running = None;
+            return None;
+        }
+        let due = self.last_scan_secs.is_none_or(|secs| now_secs.saturating_sub(secs) >= self.scan_interval_secs);
+        if due {
+            self.last_scan_secs = Some(now_secs);
+            let running = scan_game_running(fs);
+            if running != self.running {
+                self.on_running_changed(running, now_secs);
+            }
+            self.running = running;
+        }
+        self.running
+    }
+    fn on_running_changed(&mut self, running: Option<bool>, now_secs: u64) {
+        match running {
+            Some(true) => {
+                info!("Game process detected, session started");
+                self.session_started_secs = Some(now_secs);
+                self.runs_this_session = 0;
+                self.last_run_seed = None;
+                self.changed = true;
+            },
+            Some(false) if self.running == Some(true) => {
+                let duration_secs = self.session_started_secs.map(|secs| now_secs.saturating_sub(secs));
+                info!("Game process exited, session ended (duration: {} s, runs: {})",
+                      duration_secs.unwrap_or(0), self.runs_this_session);
+                self.session_started_secs = None;
+                self.last_session_secs = duration_secs;
+                self.changed = true;
+            },
+            Some(false) => debug!("Game process not running"),
+            None => debug!("Game process detection is not available"),
+        }
+    }
+
+    // 書き出したautosaveのシードが変わっていれば新しいrunとして数える
+    pub fn record_run(&mut self, seed: i64) {
+        if self.running == Some(false) || self.last_run_seed == Some(seed) {
+            return;
+        }
+        self.last_run_seed = Some(seed);
+        self.runs_this_session += 1;
+        debug!("Run {} of this session (seed: {})", self.runs_this_session, seed);
+    }
+
+    pub fn session_info(&self, now_secs: u64) -> SessionInfo {
+        let session_duration_secs = match self.session_started_secs {
+            Some(started_secs) => Some(now_secs.saturating_sub(started_secs)),
+            None => self.last_session_secs,
+        };
+        SessionInfo { game_running: self.running, session_duration_secs, runs_this_session: self.runs_this_session }
+    }
+}
+
+// オーバーレイは毎秒書き換えないよう分単位で表示
+pub fn compose_txt_session(info: &SessionInfo) -> String {
+    let duration = |secs: Option<u64>| match secs {
+        Some(secs) => format!("{}:{:02}", secs / 3600, secs / 60 % 60),
+        None => "-".to_string(),
+    };
+    match info.game_running {
+        Some(true) => format!("Game: running\nSession: {}\nRuns this session: {}\n",
+                              duration(info.session_duration_secs), info.runs_this_session),
+        Some(false) => format!("Game: not running\nLast session: {}\nRuns last session: {}\n",
+                               duration(info.session_duration_secs), info.runs_this_session),
+        None => format!("Game: unknown\nRuns this session: {}\n", info.runs_this_session),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fake::*;
+
+    const GAME_CMDLINE: &str = "jre/bin/java\0-Xmx1G\0-jar\0desktop-1.0.jar\0";
+
+    #[test]
+    fn detects_native_and_proton_game_processes() {
+        let fs = MemFileSystem::default();
+        fs.set_file("/proc/1/cmdline", "/sbin/init\0", 0);
+        fs.set_file("/proc/self/cmdline", "java\0-jar\0desktop-1.0.jar\0", 0);
+        fs.set_file("/proc/42/cmdline", "/usr/bin/python3\0SlayTheSpire.py\0", 0);
+        assert_eq!(scan_game_running(&fs), Some(false));
+
+        fs.set_file("/proc/4242/cmdline", GAME_CMDLINE, 0);
+        assert_eq!(scan_game_running(&fs), Some(true));
+
+        assert!(is_game_cmdline("Z:\\home\\steam\\SlayTheSpire\\SlayTheSpire.exe\0"));
+        assert!(is_game_cmdline("C:\\SlayTheSpire\\jre\\bin\\javaw.exe\0-jar\0desktop-1.0.jar\0"));
+    }
+
+    #[test]
+    fn unknown_without_proc() {
+        assert_eq!(scan_game_running(&MemFileSystem::default()), None);
+    }
+
+    #[test]
+    fn session_tracks_duration_and_runs() {
+        let fs = MemFileSystem::default();
+        fs.set_file("/proc/1/cmdline", "/sbin/init\0", 0);
+        let mut monitor = ProcessMonitor::new(true);
+        monitor.refresh(&fs, 1000);
+        monitor.record_run(1); // ゲームが起動していなければ数えない
+        assert!(!monitor.take_change());
+
+        fs.set_file("/proc/4242/cmdline", GAME_CMDLINE, 0);
+        monitor.refresh(&fs, 1010);
+        assert!(monitor.take_change());
+        monitor.record_run(1);
+        monitor.record_run(1);
+        monitor.record_run(2);
+        let info = monitor.session_info(1010 + 3720);
+        assert_eq!(info, SessionInfo { game_running: Some(true), session_duration_secs: Some(3720), runs_this_session: 2 });
+        assert_eq!(compose_txt_session(&info), "Game: running\nSession: 1:02\nRuns this session: 2\n");
+
+        fs.remove_file("/proc/4242/cmdline");
+        monitor.refresh(&fs, 1010 + 7200);
+        assert!(monitor.take_change());
+        assert_eq!(compose_txt_session(&monitor.session_info(99999)),
+                   "Game: not running\nLast session: 2:00\nRuns last session: 2\n");
+    }
+}
@@ -0,0 +1,144 @@
+// ゲームの状況に応じてチェック間隔を調整するモジュール
+//
+// Waiting: autosaveもゲームのプロセスもなければ間隔を指数的に延ばす(最大max_waiting_interval_ms)
+// Watching: autosaveが最近更新されていれば間隔を縮める(active_interval_ms)
+// ゲームのプロセスを検出できる環境では、起動中かどうかで監視を積極的/控えめに切り替える
+use crate::common::*;
+
+const DEFAULT_WAITING_INTERVAL_MS: u64 = 3000;
+const DEFAULT_WATCHING_INTERVAL_MS: u64 = 1000;
+const DEFAULT_ACTIVE_INTERVAL_MS: u64 = 500;
+const DEFAULT_MAX_WAITING_INTERVAL_MS: u64 = 60000;
+const DEFAULT_RECENT_CHANGE_SECS: u64 = 60;
+
+// モードごとのチェック間隔
+// (waiting_interval_ms/watching_interval_msが未指定ならloop_interval_msで代用)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Intervals {
+    pub waiting_ms: u64,     // 待機中(ゲーム未起動など)は遅めに
+    pub watching_ms: u64,    // 監視中(プレイ中)は速めに
+    pub active_ms: u64,      // 最近autosaveが更新された場合はさらに速く
+    pub max_waiting_ms: u64, // 待機中に延ばす間隔の上限
+    pub recent_change_secs: u64,
+}
+impl Intervals {
+    pub fn from_json(json_data: &JsonData) -> Self {
+        let loop_interval_ms: Option<u64> = json_data.get_value_from_key("loop_interval_ms");
+        let waiting_ms = json_data.get_value_from_key("waiting_interval_ms")
+                                  .or(loop_interval_ms)
+                                  .unwrap_or(DEFAULT_WAITING_INTERVAL_MS);
+        let watching_ms = json_data.get_value_from_key("watching_interval_ms")
+                                   .or(loop_interval_ms)
+                                   .unwrap_or(DEFAULT_WATCHING_INTERVAL_MS);
+        Self {
+            waiting_ms,
+            watching_ms,
+            active_ms: json_data.get_value_from_key("active_interval_ms")
+                                .unwrap_or(DEFAULT_ACTIVE_INTERVAL_MS)
+                                .min(watching_ms),
+            max_waiting_ms: json_data.get_value_from_key("max_waiting_interval_ms")
+                                     .unwrap_or(DEFAULT_MAX_WAITING_INTERVAL_MS)
+                                     .max(waiting_ms),
+            recent_change_secs: json_data.get_value_from_key("recent_change_secs")
+                                         .unwrap_or(DEFAULT_RECENT_CHANGE_SECS),
+        }
+    }
+}
+
+// 間隔の判断材料
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Activity {
+    pub now_secs: u64,
+    pub autosave_present: bool,
+    pub last_change_secs: Option<u64>, // autosaveの最終更新日時(UNIX時間の秒)
+    pub game_running: Option<bool>,    // プロセスを検出できない環境ではNone
+}
+impl Activity {
+    fn changed_recently(&self, within_secs: u64) -> bool {
+        self.last_change_secs.is_some_and(|secs| self.now_secs.saturating_sub(secs) < within_secs)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    intervals: Intervals,
+    backoff_level: u32,
+}
+#[rustfmt::skip]
+impl Scheduler {
+    pub fn new(intervals: Intervals) -> Self { Self { intervals, backoff_level: 0 } }
+    pub fn set_intervals(&mut self, intervals: Intervals) { self.intervals = intervals; }
+    pub fn reset_backoff(&mut self) { self.backoff_level = 0; }
+
+    pub fn waiting_delay(&mut self, activity: &Activity) -> u64 {
+        // ゲームが起動中ならいつ新しいrunが始まってもいいように速めに
+        if activity.game_running == Some(true) {
+            self.reset_backoff();
+            return self.intervals.watching_ms;
+        }
+        if activity.autosave_present {
+            self.reset_backoff();
+            return self.intervals.waiting_ms;
+        }
+        // autosaveもゲームもなければ指数バックオフ
+        let delay_ms = self.intervals.waiting_ms
+                           .checked_shl(self.backoff_level)
+                           .unwrap_or(u64::MAX)
+                           .min(self.intervals.max_waiting_ms);
+        if delay_ms < self.intervals.max_waiting_ms {
+            self.backoff_level += 1;
+        }
+        delay_ms
+    }
+    pub fn watching_delay(&mut self, activity: &Activity) -> u64 {
+        self.reset_backoff();
+        if activity.changed_recently(self.intervals.recent_change_secs) {
+            return self.intervals.active_ms;
+        }
+        // ゲームが終了していればautosaveが残っていても控えめに
+        if activity.game_running == Some(false) {
+            return self.intervals.waiting_ms;
+        }
+        self.intervals.watching_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler() -> Scheduler {
+        Scheduler::new(Intervals {
+            waiting_ms: 1000,
+            watching_ms: 500,
+            active_ms: 200,
+            max_waiting_ms: 5000,
+            recent_change_secs: 60,
+        })
+    }
+    fn activity(autosave_present: bool, last_change_secs: Option<u64>, game_running: Option<bool>) -> Activity {
+        Activity { now_secs: 1000, autosave_present, last_change_secs, game_running }
+    }
+
+    #[test]
+    fn waiting_without_autosave_backs_off_up_to_the_limit() {
+        let mut scheduler = scheduler();
+        let idle = activity(false, None, None);
+        let delays: Vec<u64> = (0..5).map(|_| scheduler.waiting_delay(&idle)).collect();
+        assert_eq!(delays, [1000, 2000, 4000, 5000, 5000]);
+
+        // autosaveかゲームが見つかればバックオフを解除
+        assert_eq!(scheduler.waiting_delay(&activity(true, None, None)), 1000);
+        assert_eq!(scheduler.waiting_delay(&idle), 1000);
+        assert_eq!(scheduler.waiting_delay(&activity(false, None, Some(true))), 500);
+        assert_eq!(scheduler.waiting_delay(&idle), 1000);
+    }
+
+    #[test]
+    fn watching_tightens_after_recent_change() {
+        let mut scheduler = scheduler();
+        assert_eq!(scheduler.watching_delay(&activity(true, Some(990), None)), 200);
+        assert_eq!(scheduler.watching_delay(&activity(true, Some(900), None)), 500);
+        assert_eq!(scheduler.watching_delay(&activity(true, Some(900), Some(false))), 1000);
+    }
+}
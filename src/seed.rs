@@ -0,0 +1,80 @@
+// runのシードを扱うモジュール
+// autosaveのseedは数値なので、ゲーム内で表示・入力する英数字の文字列に変換する
+use libsts::Save;
+use serde_json::{json, Value};
+
+use crate::modifiers::*;
+
+// ゲーム内のシード文字列で使う文字(Oは0と紛らわしいので除外された35文字)
+const SEED_CHARACTERS: &[u8; 35] = b"0123456789ABCDEFGHIJKLMNPQRSTUVWXYZ";
+
+// 数値のシードをゲーム内の文字列に変換する(符号なし64bitとして35進数表記)
+pub fn seed_to_string(seed: i64) -> String {
+    let base = SEED_CHARACTERS.len() as u64;
+    let mut leftover = seed as u64;
+    let mut chars = Vec::new();
+    while leftover > 0 {
+        chars.push(SEED_CHARACTERS[(leftover % base) as usize] as char);
+        leftover /= base;
+    }
+    chars.iter().rev().collect()
+}
+
+// シードと、それがどういうrunのものか
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSeed {
+    pub seed: String,
+    pub seeded: bool, // プレイヤーがシードを指定して開始した
+    pub daily: bool,  // デイリークライム
+    pub custom: bool, // カスタムモード
+}
+impl RunSeed {
+    // seed_setはlibstsのSaveにないので生のJSONから読む
+    pub fn new(save: &Save, raw: &Value) -> Self {
+        let kind = RunKind::of(save);
+        Self {
+            seed: seed_to_string(save.seed),
+            seeded: raw.get("seed_set").and_then(Value::as_bool).unwrap_or(false),
+            daily: kind == RunKind::Daily,
+            custom: kind == RunKind::Custom,
+        }
+    }
+    pub fn to_json(&self) -> Value {
+        json!({ "seed": self.seed, "seeded": self.seeded, "daily": self.daily, "custom": self.custom })
+    }
+}
+
+// 例: "Seed: 1A2B3C4D5E (seeded, custom)"
+pub fn compose_txt_seed(run_seed: &RunSeed) -> String {
+    let flags: Vec<&str> = [(run_seed.seeded, "seeded"), (run_seed.daily, "daily"), (run_seed.custom, "custom")]
+        .into_iter()
+        .filter_map(|(flag, name)| flag.then_some(name))
+        .collect();
+    if flags.is_empty() {
+        format!("Seed: {}\n", run_seed.seed)
+    } else {
+        format!("Seed: {} ({})\n", run_seed.seed, flags.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_seed_like_the_game() {
+        assert_eq!(seed_to_string(34), "Z");
+        assert_eq!(seed_to_string(35), "10");
+        assert_eq!(seed_to_string(1234567890123), "J6KSGCJ8");
+        // 負の値は符号なしとして扱う
+        assert_eq!(seed_to_string(-1), "5G24A25UXKXFF");
+    }
+
+    #[test]
+    fn flags_are_listed_after_the_seed() {
+        let run_seed = RunSeed { seed: "ABC".to_string(), seeded: true, daily: false, custom: true };
+        assert_eq!(compose_txt_seed(&run_seed), "Seed: ABC (seeded, custom)\n");
+        let run_seed = RunSeed { seeded: false, custom: false, ..run_seed };
+        assert_eq!(compose_txt_seed(&run_seed), "Seed: ABC\n");
+    }
+}
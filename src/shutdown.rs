@@ -0,0 +1,11 @@
+// Ctrl-C/SIGTERMによる終了要求を扱うモジュール
+// シグナルハンドラはフラグを立てるだけで、実際の終了処理はメインループ側で行う
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+pub fn install_handler() -> Result<(), String> {
+    ctrlc::set_handler(|| REQUESTED.store(true, Ordering::SeqCst)).map_err(|e| e.to_string())
+}
+
+pub fn requested() -> bool { REQUESTED.load(Ordering::SeqCst) }
@@ -0,0 +1,93 @@
+// 実行時の状態(最後に観測したautosaveの情報など)を扱うモジュール
+// ユーザーが編集するsettings.jsonとは別ファイル(データディレクトリ内)に保存する
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::common::*;
+use crate::system::*;
+
+const DEFAULT_DATA_DIR_PATH: &str = "./data/";
+const STATE_FILE_NAME: &str = "state.json";
+
+// 状態ファイルに保存する値
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct State {
+    modified_time: u64,
+    character_type: String,
+}
+
+// 状態ファイルのパラメータを格納する構造体
+#[derive(Debug, Clone)]
+pub struct StateData {
+    path: PathBuf,
+    state: State,
+    write_stats: WriteStats,
+}
+#[rustfmt::skip]
+impl StateData {
+    pub fn load(fs: &impl FileSystem, json_data: &JsonData) -> Self {
+        let data_dir_path = data_dir_path(json_data);
+        if let Err(e) = fs.create_dir_all(&data_dir_path) {
+            error!("Failed to create data directory {}: {}", data_dir_path.display(), e);
+        }
+        let path = data_dir_path.join(STATE_FILE_NAME);
+        let state = match fs.read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring broken state file {}: {}", path.display(), e);
+                State::default()
+            }),
+            // 状態ファイルがまだ無ければ旧バージョンのsettings.jsonの値を引き継ぐ
+            Err(_) => State {
+                modified_time: json_data.get_value_from_key("modified_time").unwrap_or_default(),
+                character_type: json_data.get_value_from_key("character_type").unwrap_or_default(),
+            },
+        };
+        Self { path, state, write_stats: WriteStats::default() }
+    }
+    // getter
+    pub fn modified_time(&self) -> u64 { self.state.modified_time }
+    pub fn character_type(&self) -> String { self.state.character_type.clone() }
+    // 前回取り出してからの書き出し結果
+    pub fn take_write_stats(&mut self) -> WriteStats { std::mem::take(&mut self.write_stats) }
+
+    // 監視対象の値で状態を更新して保存
+    pub fn update(&mut self, fs: &impl FileSystem, target: &Target) {
+        self.state.modified_time = target.modified_time();
+        self.state.character_type = target.character_type();
+        self.save(fs); // 失敗はWriteStatsでログ出力済み
+    }
+    // 保存に成功(または内容が同じでスキップ)すればtrue
+    pub fn save(&mut self, fs: &impl FileSystem) -> bool {
+        match serde_json::to_string_pretty(&self.state) {
+            Ok(body) => {
+                let result = fs.write_atomic(&self.path, body.as_bytes());
+                let succeeded = result.is_ok();
+                self.write_stats.record("state json", result);
+                succeeded
+            },
+            Err(e) => {
+                error!("Failed to serialize state: {}", e);
+                false
+            },
+        }
+    }
+    // トレイト境界AsRef<Path>により、Path型もPathBuf型も両方受け取れる
+    pub fn compare_modified_time<P: AsRef<Path>>(&self, fs: &impl FileSystem, autosave_path: &P) -> ModifiedTimeStatus {
+        let Ok(autosave_modified_time) = get_file_modified_time(fs, autosave_path) else {
+            return ModifiedTimeStatus::Missing;
+        };
+
+        match autosave_modified_time.cmp(&self.state.modified_time) {
+            std::cmp::Ordering::Less => ModifiedTimeStatus::Old,
+            std::cmp::Ordering::Greater => ModifiedTimeStatus::New,
+            std::cmp::Ordering::Equal => ModifiedTimeStatus::Equal,
+        }
+    }
+}
+
+// データディレクトリ(未指定なら設定ファイルの隣の既定値)
+pub fn data_dir_path(json_data: &JsonData) -> PathBuf {
+    json_data.get_path_from_key("data_dir_path")
+             .unwrap_or_else(|| json_data.resolve_path(PathBuf::from(DEFAULT_DATA_DIR_PATH)))
+}
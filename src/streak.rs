@@ -0,0 +1,411 @@
+// 連勝記録(ストリーク)を扱うモジュール
+//
+// runの結果はゲームがrunsディレクトリに書き出す.runファイル(runs/<キャラクター>/<終了時刻>.run)から読む
+// autosaveが消えたあとに新しい.runファイルを数え、ルールに従って連勝数を更新してデータディレクトリに保存する
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::common::*;
+use crate::state::*;
+use crate::system::*;
+
+const STREAK_FILE_NAME: &str = "streak.json";
+const RUNS_DIR_NAME: &str = "runs";
+const RUN_FILE_EXTENSION: &str = "run";
+// 取り消し用に残す記録の数
+const LOG_LEN: usize = 50;
+// キャラクターを区別しないルールで使うキー
+const ANY_KEY: &str = "ANY";
+const ROTATING_KEY: &str = "ROTATING";
+
+// 連勝の数え方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreakMode {
+    PerCharacter, // キャラクターごとに数える
+    Rotating,     // streak_rotationの順にキャラクターを替えて勝ち続ける(順番外のrunは数えない)
+    Any,          // キャラクターを区別しない
+}
+impl StreakMode {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "per_character" => Some(Self::PerCharacter),
+            "rotating" => Some(Self::Rotating),
+            "any" => Some(Self::Any),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreakRules {
+    pub mode: StreakMode,
+    pub min_ascension: u32, // これより低いアセンションのrunは数えない
+    pub rotation: Vec<String>,
+}
+impl StreakRules {
+    pub fn from_json(json_data: &JsonData) -> Self {
+        let mode: Option<String> = json_data.get_value_from_key("streak_mode");
+        Self {
+            mode: mode.as_deref().and_then(StreakMode::parse).unwrap_or(StreakMode::PerCharacter),
+            min_ascension: json_data.get_value_from_key("streak_min_ascension").unwrap_or(0),
+            rotation: json_data.get_value_from_key("streak_rotation")
+                               .unwrap_or_else(|| CHARACTERS.map(String::from).to_vec()),
+        }
+    }
+    // 連勝数を記録するキー
+    fn key_of(&self, character: &str) -> String {
+        match self.mode {
+            StreakMode::PerCharacter => character.to_string(),
+            StreakMode::Rotating => ROTATING_KEY.to_string(),
+            StreakMode::Any => ANY_KEY.to_string(),
+        }
+    }
+}
+
+// .runファイルから読んだrunの結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunOutcome {
+    pub timestamp: u64,
+    pub character: String,
+    pub ascension: u32,
+    pub victory: bool,
+    pub daily: bool,
+    pub custom: bool,
+}
+impl RunOutcome {
+    pub fn from_json(timestamp: u64, run: &Value) -> Option<Self> {
+        Some(Self {
+            timestamp,
+            character: run.get("character_chosen")?.as_str()?.to_string(),
+            ascension: run.get("ascension_level").and_then(Value::as_u64).unwrap_or(0) as u32,
+            victory: run.get("victory").and_then(Value::as_bool).unwrap_or(false),
+            daily: run.get("is_daily").and_then(Value::as_bool).unwrap_or(false),
+            custom: run.get("is_trial").and_then(Value::as_bool).unwrap_or(false),
+        })
+    }
+}
+
+pub fn runs_dir_path(json_data: &JsonData) -> PathBuf {
+    game_dir_path(json_data, "runs_dir_path", RUNS_DIR_NAME)
+}
+
+// runsディレクトリ内の.runファイルを(終了時刻, パス)の古い順で返す(ファイル名が終了時刻)
+fn list_run_files(fs: &impl FileSystem, runs_dir_path: &Path) -> std::io::Result<Vec<(u64, PathBuf)>> {
+    let mut run_files = Vec::new();
+    for character_dir in fs.read_dir(runs_dir_path)? {
+        let Ok(entries) = fs.read_dir(&character_dir) else {
+            continue;
+        };
+        run_files.extend(entries.into_iter().filter_map(|path| {
+            if path.extension()? != RUN_FILE_EXTENSION {
+                return None;
+            }
+            let timestamp = path.file_stem()?.to_str()?.parse().ok()?;
+            Some((timestamp, path))
+        }));
+    }
+    run_files.sort();
+    Ok(run_files)
+}
+
+// 連勝数(取り消しのために丸ごと記録する)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct Counters {
+    current: BTreeMap<String, u32>,
+    best: BTreeMap<String, u32>,
+    next_in_rotation: usize,
+}
+
+// 連勝数を変えた操作1回分(変更前の連勝数つき)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct LogEntry {
+    description: String,
+    before: Counters,
+}
+
+// 保存ファイルの中身
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct StreakState {
+    last_run_timestamp: Option<u64>, // 数え終わった最新の.runファイル(Noneなら未初期化)
+    counters: Counters,
+    log: Vec<LogEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StreakTracker {
+    path: PathBuf,
+    state: StreakState,
+    write_stats: WriteStats,
+}
+#[rustfmt::skip]
+impl StreakTracker {
+    pub fn load(fs: &impl FileSystem, json_data: &JsonData) -> Self {
+        let path = data_dir_path(json_data).join(STREAK_FILE_NAME);
+        let state = match fs.read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                warn!("Ignoring broken streak file {}: {}", path.display(), e);
+                StreakState::default()
+            }),
+            Err(_) => StreakState::default(),
+        };
+        Self { path, state, write_stats: WriteStats::default() }
+    }
+    // 保存に成功(または内容が同じでスキップ)すればtrue
+    pub fn save(&mut self, fs: &impl FileSystem) -> bool {
+        match serde_json::to_string_pretty(&self.state) {
+            Ok(body) => {
+                let result = fs.write_atomic(&self.path, body.as_bytes());
+                let succeeded = result.is_ok();
+                self.write_stats.record("streak json", result);
+                succeeded
+            },
+            Err(e) => {
+                error!("Failed to serialize streak: {}", e);
+                false
+            },
+        }
+    }
+    // getter
+    pub fn current(&self, key: &str) -> u32 { self.state.counters.current.get(key).copied().unwrap_or(0) }
+    pub fn best(&self, key: &str) -> u32 { self.state.counters.best.get(key).copied().unwrap_or(0) }
+    pub fn take_write_stats(&mut self) -> WriteStats { std::mem::take(&mut self.write_stats) }
+
+    /* 前回以降に増えた.runファイルを数えて、変化があれば保存する
+
+      初回は既存の.runファイルを数えず、以降に終わったrunから数え始める
+    */
+    pub fn update_from_runs(&mut self, fs: &impl FileSystem, runs_dir_path: &Path, rules: &StreakRules) {
+        // runsディレクトリがまだ無ければ(初めてのrunの前など)、後から現れる.runファイルはすべて新しいrun
+        let run_files = list_run_files(fs, runs_dir_path).unwrap_or_default();
+        let Some(last_run_timestamp) = self.state.last_run_timestamp else {
+            self.state.last_run_timestamp = Some(run_files.last().map_or(0, |(timestamp, _)| *timestamp));
+            info!("Streak tracking started, counting runs finished from now on");
+            self.save(fs);
+            return;
+        };
+        let new_runs: Vec<_> = run_files.into_iter().filter(|(timestamp, _)| *timestamp > last_run_timestamp).collect();
+        let Some((newest, _)) = new_runs.last() else {
+            return;
+        };
+        self.state.last_run_timestamp = Some(*newest);
+        for (timestamp, path) in &new_runs {
+            let run = fs.read_to_string(path)
+                        .ok()
+                        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+                        .and_then(|run| RunOutcome::from_json(*timestamp, &run));
+            match run {
+                Some(run) => {
+                    self.record(rules, &run);
+                },
+                None => warn!("Ignoring unreadable run file: {}", path.display()),
+            }
+        }
+        self.save(fs);
+    }
+
+    // runの結果をルールに従って反映する(数えないrunならfalse)
+    pub fn record(&mut self, rules: &StreakRules, run: &RunOutcome) -> bool {
+        if run.daily || run.custom || run.ascension < rules.min_ascension {
+            debug!("Run not counted for the streak: {} A{} (daily: {}, custom: {})",
+                   run.character, run.ascension, run.daily, run.custom);
+            return false;
+        }
+        if rules.mode == StreakMode::Rotating {
+            let expected = &rules.rotation[self.state.counters.next_in_rotation % rules.rotation.len()];
+            if *expected != run.character {
+                info!("Run not counted for the rotating streak: expected {}, played {}", expected, run.character);
+                return false;
+            }
+        }
+        let result = if run.victory { "win" } else { "loss" };
+        self.push_log(format!("{} {} A{} at {}", run.character, result, run.ascension, run.timestamp));
+
+        let key = rules.key_of(&run.character);
+        let counters = &mut self.state.counters;
+        if run.victory {
+            let current = counters.current.entry(key.clone()).or_default();
+            *current += 1;
+            let best = counters.best.entry(key.clone()).or_default();
+            *best = (*best).max(*current);
+            counters.next_in_rotation = (counters.next_in_rotation + 1) % rules.rotation.len();
+        } else {
+            counters.current.insert(key.clone(), 0);
+            counters.next_in_rotation = 0;
+        }
+        info!("Streak updated by a {}: {} {}", result, key, self.current(&key));
+        true
+    }
+    // 手動での修正(誤って放棄したrunなど)
+    pub fn set(&mut self, key: &str, streak: u32) {
+        self.push_log(format!("set {} to {}", key, streak));
+        let counters = &mut self.state.counters;
+        counters.current.insert(key.to_string(), streak);
+        let best = counters.best.entry(key.to_string()).or_default();
+        *best = (*best).max(streak);
+    }
+    // 直前の変更を取り消して、その内容を返す
+    pub fn undo(&mut self) -> Option<String> {
+        let entry = self.state.log.pop()?;
+        self.state.counters = entry.before;
+        Some(entry.description)
+    }
+    fn push_log(&mut self, description: String) {
+        if self.state.log.len() == LOG_LEN {
+            self.state.log.remove(0);
+        }
+        let before = self.state.counters.clone();
+        self.state.log.push(LogEntry { description, before });
+    }
+}
+
+/* sts_streak.txtの内容
+
+  per_character: キャラクターごとに "IRONCLAD: 3 (best: 5)"
+  rotating: "Rotating streak: 3 (best: 5)" と次のキャラクター
+  any: "Streak: 3 (best: 5)"
+  アセンションの下限があれば見出しに添える
+*/
+pub fn compose_txt_streak(tracker: &StreakTracker, rules: &StreakRules) -> String {
+    let mut ret = String::new();
+    let min_ascension = match rules.min_ascension {
+        0 => String::new(),
+        n => format!(" (A{}+)", n),
+    };
+    match rules.mode {
+        StreakMode::PerCharacter => {
+            ret.push_str(&format!("Win streaks{}\n", min_ascension));
+            for character in &rules.rotation {
+                ret.push_str(&format!("{}: {} (best: {})\n", character, tracker.current(character), tracker.best(character)));
+            }
+        },
+        StreakMode::Rotating => {
+            ret.push_str(&format!("Rotating streak{}: {} (best: {})\n",
+                                  min_ascension, tracker.current(ROTATING_KEY), tracker.best(ROTATING_KEY)));
+            let next = &rules.rotation[tracker.state.counters.next_in_rotation % rules.rotation.len()];
+            ret.push_str(&format!("Next: {}\n", next));
+        },
+        StreakMode::Any => {
+            ret.push_str(&format!("Streak{}: {} (best: {})\n", min_ascension, tracker.current(ANY_KEY), tracker.best(ANY_KEY)));
+        },
+    }
+    ret
+}
+
+/* streakサブコマンド: 連勝数の表示と手動での修正
+
+  streak             現在の連勝数を表示
+  streak undo        直前の変更(記録されたrunや手動の修正)を取り消す
+  streak set N [CH]  連勝数をNにする(per_characterではキャラクターの指定が必要)
+*/
+pub fn run_streak_command(fs: &impl FileSystem, json_data: &JsonData, args: &[String]) -> Result<String, String> {
+    const USAGE: &str = "Usage: sts-observer streak [undo | set <streak> [<character>]]";
+    let rules = StreakRules::from_json(json_data);
+    let mut tracker = StreakTracker::load(fs, json_data);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let message = match args.as_slice() {
+        [] => String::new(),
+        ["undo"] => match tracker.undo() {
+            Some(description) => format!("Undone: {}\n", description),
+            None => return Err(String::from("Nothing to undo")),
+        },
+        ["set", streak, character @ ..] => {
+            let streak: u32 = streak.parse().map_err(|_| format!("Invalid streak: {}", streak))?;
+            let key = match (rules.mode, character) {
+                (StreakMode::PerCharacter, [character]) => {
+                    let character = character.to_uppercase().replace(' ', "_");
+                    if !rules.rotation.contains(&character) {
+                        return Err(format!("Unknown character: {} (expected one of: {})", character, rules.rotation.join(", ")));
+                    }
+                    character
+                },
+                (StreakMode::PerCharacter, _) => return Err(format!("{} (the character is required for per_character streaks)", USAGE)),
+                (_, []) => rules.key_of(""),
+                (_, _) => return Err(USAGE.to_string()),
+            };
+            tracker.set(&key, streak);
+            format!("Set {} to {}\n", key, streak)
+        },
+        _ => return Err(USAGE.to_string()),
+    };
+    if !args.is_empty() && !tracker.save(fs) {
+        return Err(String::from("Failed to save the streak"));
+    }
+    Ok(message + &compose_txt_streak(&tracker, &rules))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fake::*;
+    use serde_json::json;
+
+    fn run(character: &str, victory: bool) -> RunOutcome {
+        RunOutcome { timestamp: 0, character: character.to_string(), ascension: 10, victory, daily: false, custom: false }
+    }
+
+    #[test]
+    fn rotating_streak_follows_the_rotation_and_can_be_undone() {
+        let json_data = JsonData::from_value(json!({ "data_dir_path": "/data/", "streak_mode": "rotating" }));
+        let rules = StreakRules::from_json(&json_data);
+        let mut tracker = StreakTracker::load(&MemFileSystem::default(), &json_data);
+        assert!(tracker.record(&rules, &run("IRONCLAD", true)));
+        assert!(!tracker.record(&rules, &run("WATCHER", true)));
+        assert!(tracker.record(&rules, &run("THE_SILENT", true)));
+        assert_eq!(compose_txt_streak(&tracker, &rules), "Rotating streak: 2 (best: 2)\nNext: DEFECT\n");
+
+        // 誤って放棄したrunを取り消す
+        assert!(tracker.record(&rules, &run("DEFECT", false)));
+        assert_eq!(tracker.current(ROTATING_KEY), 0);
+        assert_eq!(tracker.undo().as_deref(), Some("DEFECT loss A10 at 0"));
+        assert_eq!(compose_txt_streak(&tracker, &rules), "Rotating streak: 2 (best: 2)\nNext: DEFECT\n");
+    }
+
+    #[test]
+    fn new_run_files_update_per_character_streaks() {
+        let fs = MemFileSystem::default();
+        fs.set_file("/runs/IRONCLAD/1000.run", r#"{"character_chosen": "IRONCLAD", "victory": true}"#, 0);
+        let json_data = JsonData::from_value(json!({
+            "autosave_dir_path": "/saves/",
+            "data_dir_path": "/data/",
+            "streak_min_ascension": 5,
+        }));
+        let rules = StreakRules::from_json(&json_data);
+        let mut tracker = StreakTracker::load(&fs, &json_data);
+        // 既存のrunは数えない
+        tracker.update_from_runs(&fs, &runs_dir_path(&json_data), &rules);
+        assert_eq!(tracker.current("IRONCLAD"), 0);
+
+        fs.set_file("/runs/IRONCLAD/2000.run", r#"{"character_chosen": "IRONCLAD", "ascension_level": 5, "victory": true}"#, 0);
+        fs.set_file("/runs/DEFECT/3000.run", r#"{"character_chosen": "DEFECT", "ascension_level": 4, "victory": true}"#, 0);
+        tracker.update_from_runs(&fs, &runs_dir_path(&json_data), &rules);
+        let mut tracker = StreakTracker::load(&fs, &json_data);
+        assert_eq!((tracker.current("IRONCLAD"), tracker.current("DEFECT")), (1, 0));
+        tracker.update_from_runs(&fs, &runs_dir_path(&json_data), &rules);
+        assert_eq!(tracker.current("IRONCLAD"), 1);
+
+        let set = |args: &[&str]| run_streak_command(&fs, &json_data, &args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>());
+        assert!(set(&["set", "3", "silent"]).unwrap_err().starts_with("Unknown character: SILENT"));
+        let output = set(&["set", "4", "defect"]).unwrap();
+        assert!(output.starts_with("Set DEFECT to 4\nWin streaks (A5+)\nIRONCLAD: 1 (best: 1)\n"), "{}", output);
+        assert_eq!(StreakTracker::load(&fs, &json_data).current("DEFECT"), 4);
+    }
+
+    #[test]
+    fn first_run_counts_when_runs_dir_did_not_exist_yet() {
+        let fs = MemFileSystem::default();
+        let json_data = JsonData::from_value(json!({ "autosave_dir_path": "/saves/", "data_dir_path": "/data/" }));
+        let rules = StreakRules::from_json(&json_data);
+        let mut tracker = StreakTracker::load(&fs, &json_data);
+        tracker.update_from_runs(&fs, &runs_dir_path(&json_data), &rules);
+
+        fs.set_file("/runs/WATCHER/1000.run", r#"{"character_chosen": "WATCHER", "victory": true}"#, 0);
+        let mut tracker = StreakTracker::load(&fs, &json_data);
+        tracker.update_from_runs(&fs, &runs_dir_path(&json_data), &rules);
+        assert_eq!(tracker.current("WATCHER"), 1);
+    }
+}
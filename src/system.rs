@@ -0,0 +1,238 @@
+// ファイルシステムと時計の抽象化
+// 監視ロジック(Observer)はこれらのトレイトを介してのみ外部とやり取りするので、
+// テストではインメモリの偽実装に差し替えて状態遷移を決定的に再現できる
+use std::fs::{self};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::shutdown;
+
+// 書き出し結果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WriteOutcome {
+    Written,
+    Unchanged,
+}
+
+// ファイルの変更検知に使うメタデータ
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileMeta {
+    pub modified: SystemTime,
+    pub len: u64,
+    pub is_file: bool,
+}
+impl FileMeta {
+    // 更新日時(UNIX時間の秒)
+    pub fn modified_secs(&self) -> u64 {
+        self.modified.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+    }
+}
+
+pub trait FileSystem {
+    fn metadata(&self, path: &Path) -> io::Result<FileMeta>;
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    // ディレクトリ直下のエントリのパス一覧
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>>;
+    // 一時ファイル+fsync+renameによるクラッシュセーフな書き出し(内容が同じならスキップ)
+    fn write_atomic(&self, path: &Path, contents: &[u8]) -> io::Result<WriteOutcome>;
+    // ログファイル向けの追記(ファイルがなければ作る)
+    fn append(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok_and(|meta| meta.is_file)
+    }
+}
+
+pub trait Clock {
+    // 現在時刻(UNIX時間の秒)
+    fn now_secs(&self) -> u64;
+    fn sleep_ms(&self, time_ms: u64);
+}
+
+// 実際のファイルシステム
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealFileSystem;
+impl FileSystem for RealFileSystem {
+    fn metadata(&self, path: &Path) -> io::Result<FileMeta> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileMeta { modified: metadata.modified()?, len: metadata.len(), is_file: metadata.is_file() })
+    }
+    fn read_to_string(&self, path: &Path) -> io::Result<String> { fs::read_to_string(path) }
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> { fs::create_dir_all(path) }
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::read_dir(path)?.map(|entry| entry.map(|entry| entry.path())).collect()
+    }
+    fn write_atomic(&self, path: &Path, contents: &[u8]) -> io::Result<WriteOutcome> {
+        if let Ok(current) = fs::read(path) {
+            if current == contents {
+                return Ok(WriteOutcome::Unchanged);
+            }
+        }
+        let file_name = path.file_name()
+                            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+        let mut tmp_name = std::ffi::OsString::from(".");
+        tmp_name.push(file_name);
+        tmp_name.push(".tmp");
+        let tmp_path = path.with_file_name(tmp_name);
+
+        let result = (|| {
+            let mut tmp_file = fs::File::create(&tmp_path)?;
+            tmp_file.write_all(contents)?;
+            tmp_file.sync_all()?;
+            fs::rename(&tmp_path, path)
+        })();
+        if let Err(e) = result {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+        // rename自体を永続化するためにディレクトリもfsync(Unix系のみ)
+        #[cfg(unix)]
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            let _ = fs::File::open(dir).and_then(|dir| dir.sync_all());
+        }
+        Ok(WriteOutcome::Written)
+    }
+    fn append(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::OpenOptions::new().create(true).append(true).open(path)?.write_all(contents)
+    }
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> { fs::rename(from, to) }
+    fn remove_file(&self, path: &Path) -> io::Result<()> { fs::remove_file(path) }
+}
+
+// 実際の時計
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+// 終了要求があれば待機を切り上げる(最大でWAIT_SLICE_MSの遅れで応答)
+const WAIT_SLICE_MS: u64 = 100;
+impl Clock for SystemClock {
+    fn now_secs(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+    }
+    fn sleep_ms(&self, time_ms: u64) {
+        let mut remaining_ms = time_ms;
+        while remaining_ms > 0 && !shutdown::requested() {
+            let slice_ms = remaining_ms.min(WAIT_SLICE_MS);
+            std::thread::sleep(Duration::from_millis(slice_ms));
+            remaining_ms -= slice_ms;
+        }
+    }
+}
+
+// テスト用のインメモリ実装
+#[cfg(test)]
+pub mod fake {
+    use super::*;
+    use std::cell::RefCell;
+    use std::cell::Cell;
+    use std::collections::{BTreeSet, HashMap};
+
+    #[derive(Debug, Clone)]
+    struct MemFile {
+        contents: String,
+        modified_secs: u64,
+    }
+
+    // インメモリのファイルシステム(ディレクトリは区別せず、ファイルだけを持つ)
+    #[derive(Debug, Default)]
+    pub struct MemFileSystem {
+        files: RefCell<HashMap<PathBuf, MemFile>>,
+        read_only_dir: RefCell<Option<PathBuf>>, // このディレクトリ以下への書き出しは失敗する
+    }
+    impl MemFileSystem {
+        pub fn set_file<P: AsRef<Path>>(&self, path: P, contents: &str, modified_secs: u64) {
+            let file = MemFile { contents: contents.to_string(), modified_secs };
+            self.files.borrow_mut().insert(path.as_ref().to_path_buf(), file);
+        }
+        pub fn remove_file<P: AsRef<Path>>(&self, path: P) {
+            self.files.borrow_mut().remove(path.as_ref());
+        }
+        pub fn contents<P: AsRef<Path>>(&self, path: P) -> Option<String> {
+            self.files.borrow().get(path.as_ref()).map(|file| file.contents.clone())
+        }
+        pub fn set_read_only<P: AsRef<Path>>(&self, dir: P) {
+            *self.read_only_dir.borrow_mut() = Some(dir.as_ref().to_path_buf());
+        }
+    }
+    impl FileSystem for MemFileSystem {
+        fn metadata(&self, path: &Path) -> io::Result<FileMeta> {
+            match self.files.borrow().get(path) {
+                Some(file) => Ok(FileMeta {
+                    modified: UNIX_EPOCH + Duration::from_secs(file.modified_secs),
+                    len: file.contents.len() as u64,
+                    is_file: true,
+                }),
+                None => Err(io::Error::from(io::ErrorKind::NotFound)),
+            }
+        }
+        fn read_to_string(&self, path: &Path) -> io::Result<String> {
+            self.contents(path).ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+        fn create_dir_all(&self, _path: &Path) -> io::Result<()> { Ok(()) }
+        // ファイルのパスから直下のエントリを導出する(エントリがなければ存在しないディレクトリ扱い)
+        fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+            let entries: BTreeSet<PathBuf> = self.files.borrow()
+                                                 .keys()
+                                                 .filter_map(|file| file.strip_prefix(path).ok())
+                                                 .filter_map(|rest| rest.components().next())
+                                                 .map(|name| path.join(name))
+                                                 .collect();
+            if entries.is_empty() {
+                return Err(io::Error::from(io::ErrorKind::NotFound));
+            }
+            Ok(entries.into_iter().collect())
+        }
+        fn write_atomic(&self, path: &Path, contents: &[u8]) -> io::Result<WriteOutcome> {
+            if self.read_only_dir.borrow().as_ref().is_some_and(|dir| path.starts_with(dir)) {
+                return Err(io::Error::from(io::ErrorKind::PermissionDenied));
+            }
+            let contents = String::from_utf8_lossy(contents).to_string();
+            if self.contents(path).as_ref() == Some(&contents) {
+                return Ok(WriteOutcome::Unchanged);
+            }
+            self.set_file(path, &contents, 0);
+            Ok(WriteOutcome::Written)
+        }
+        fn append(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+            let contents = self.contents(path).unwrap_or_default() + &String::from_utf8_lossy(contents);
+            self.set_file(path, &contents, 0);
+            Ok(())
+        }
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let file = self.files.borrow_mut().remove(from).ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+            self.files.borrow_mut().insert(to.to_path_buf(), file);
+            Ok(())
+        }
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            self.files.borrow_mut().remove(path).map(|_| ()).ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+    }
+
+    // 実際には待たず、待った分だけ時刻を進める時計
+    #[derive(Debug, Default)]
+    pub struct FakeClock {
+        now_ms: Cell<u64>,
+    }
+    impl FakeClock {
+        pub fn at(now_secs: u64) -> Self { Self { now_ms: Cell::new(now_secs * 1000) } }
+        pub fn set_secs(&self, now_secs: u64) { self.now_ms.set(now_secs * 1000); }
+    }
+    impl Clock for FakeClock {
+        fn now_secs(&self) -> u64 { self.now_ms.get() / 1000 }
+        fn sleep_ms(&self, time_ms: u64) { self.now_ms.set(self.now_ms.get() + time_ms); }
+    }
+}
+
+// テスト用のautosave(example/IRONCLAD.autosave)
+#[cfg(test)]
+pub mod fixture {
+    use libsts::Save;
+
+    pub const AUTOSAVE: &str = include_str!("../example/IRONCLAD.autosave");
+
+    // テストごとにフィールドを書き換えて使う
+    pub fn example_save() -> Save { Save::new(AUTOSAVE).unwrap() }
+}
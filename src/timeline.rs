@@ -0,0 +1,218 @@
+// autosaveのmetric_*配列をフロアごとの記録に組み直すモジュール
+//
+// metric_*_per_floorは1階から順に1要素ずつ、それ以外(被ダメージ・カード選択など)は
+// floorフィールドを持つ配列なので、フロア番号で突き合わせる
+use libsts::Save;
+use serde::Serialize;
+
+// 各幕のフロア数(ボス後の宝箱フロアまで)
+const FLOORS_PER_ACT: u32 = 17;
+const LAST_ACT: u32 = 4;
+
+// フロア1つ分の記録
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FloorRecord {
+    pub floor: u32,
+    pub act: u32,
+    pub room: Option<String>,
+    pub gold: Option<u32>,
+    pub current_hp: Option<u32>,
+    pub max_hp: Option<u32>,
+    pub damage_taken: f64,
+    pub enemies: Option<String>,
+    pub turns: Option<f64>,
+    pub card_picked: Option<String>,
+    pub cards_skipped: Vec<String>,
+    pub relics: Vec<String>,
+}
+
+// 1回の戦闘で受けた最大のダメージ
+#[derive(Debug, Clone, PartialEq)]
+pub struct BiggestHit {
+    pub damage: f64,
+    pub enemies: String,
+    pub floor: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RunTimeline {
+    pub floors: Vec<FloorRecord>,
+}
+#[rustfmt::skip]
+impl RunTimeline {
+    pub fn new(save: &Save) -> Self {
+        let reached = save.floor_num.max(0.0) as u32;
+        let per_floor_len = [
+            save.metric_path_per_floor.len(),
+            save.metric_gold_per_floor.len(),
+            save.metric_current_hp_per_floor.len(),
+            save.metric_max_hp_per_floor.len(),
+        ];
+        let last_floor = per_floor_len.into_iter().max().unwrap_or(0).max(reached as usize) as u32;
+        let mut floors: Vec<FloorRecord> = (1..=last_floor)
+            .map(|floor| {
+                let i = (floor - 1) as usize;
+                FloorRecord {
+                    floor,
+                    act: act_of_floor(floor),
+                    room: save.metric_path_per_floor.get(i).cloned().flatten(),
+                    gold: save.metric_gold_per_floor.get(i).copied(),
+                    current_hp: save.metric_current_hp_per_floor.get(i).copied(),
+                    max_hp: save.metric_max_hp_per_floor.get(i).copied(),
+                    ..FloorRecord::default()
+                }
+            })
+            .collect();
+        for damage in &save.metric_damage_taken {
+            if let Some(record) = record_at(&mut floors, damage.floor) {
+                record.damage_taken += damage.damage;
+                record.enemies = Some(damage.enemies.clone());
+                record.turns = Some(record.turns.unwrap_or(0.0) + damage.turns);
+            }
+        }
+        for choice in &save.metric_card_choices {
+            if let Some(record) = record_at(&mut floors, choice.floor) {
+                // "SKIP"は何も取らなかったことを表す
+                record.card_picked = Some(choice.picked.clone()).filter(|picked| picked != "SKIP");
+                record.cards_skipped.extend(choice.not_picked.iter().cloned());
+            }
+        }
+        for relic in &save.metric_relics_obtained {
+            if let Some(record) = record_at(&mut floors, relic.floor) {
+                record.relics.push(relic.key.clone());
+            }
+        }
+        // ボスレリックはフロアが記録されないので各幕の最後のフロアで取ったことにする
+        for (n, choice) in save.metric_boss_relics.iter().enumerate() {
+            if let (Some(picked), Some(record)) = (&choice.picked, record_at(&mut floors, ((n as u32 + 1) * FLOORS_PER_ACT) as f64)) {
+                record.relics.push(picked.clone());
+            }
+        }
+        Self { floors }
+    }
+
+    pub fn damage_taken_in_act(&self, act: u32) -> f64 {
+        self.floors.iter().filter(|record| record.act == act).fold(0.0, |sum, record| sum + record.damage_taken)
+    }
+    pub fn damage_taken_total(&self) -> f64 { self.floors.iter().fold(0.0, |sum, record| sum + record.damage_taken) }
+    pub fn biggest_hit_in_act(&self, act: u32) -> Option<BiggestHit> {
+        self.floors.iter()
+            .filter(|record| record.act == act && record.damage_taken > 0.0)
+            .max_by(|a, b| a.damage_taken.total_cmp(&b.damage_taken))
+            .map(|record| BiggestHit {
+                damage: record.damage_taken,
+                enemies: record.enemies.clone().unwrap_or_default(),
+                floor: record.floor,
+            })
+    }
+}
+
+fn record_at(floors: &mut [FloorRecord], floor: f64) -> Option<&mut FloorRecord> {
+    floors.get_mut((floor as usize).checked_sub(1)?)
+}
+
+pub fn act_of_floor(floor: u32) -> u32 { (floor.saturating_sub(1) / FLOORS_PER_ACT + 1).min(LAST_ACT) }
+
+// CSV(1行1フロア、リストは";"区切り)
+pub fn compose_csv_timeline(timeline: &RunTimeline) -> String {
+    let mut ret = String::from("floor,act,room,gold,current_hp,max_hp,damage_taken,enemies,turns,card_picked,cards_skipped,relics\n");
+    let opt = |value: Option<String>| value.unwrap_or_default();
+    for record in &timeline.floors {
+        let fields = [
+            record.floor.to_string(),
+            record.act.to_string(),
+            opt(record.room.clone()),
+            opt(record.gold.map(|n| n.to_string())),
+            opt(record.current_hp.map(|n| n.to_string())),
+            opt(record.max_hp.map(|n| n.to_string())),
+            record.damage_taken.to_string(),
+            opt(record.enemies.clone()),
+            opt(record.turns.map(|n| n.to_string())),
+            opt(record.card_picked.clone()),
+            record.cards_skipped.join(";"),
+            record.relics.join(";"),
+        ];
+        let line: Vec<String> = fields.iter().map(|field| csv_escape(field)).collect();
+        ret.push_str(&line.join(","));
+        ret.push('\n');
+    }
+    ret
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+// 例: "Damage taken this act: 87; biggest hit: Gremlin Nob, floor 6"
+pub fn compose_txt_timeline(timeline: &RunTimeline, act: u32) -> String {
+    let mut ret = format!("Damage taken this act: {}", timeline.damage_taken_in_act(act));
+    match timeline.biggest_hit_in_act(act) {
+        Some(hit) => ret.push_str(&format!("; biggest hit: {}, floor {} ({})\n", hit.enemies, hit.floor, hit.damage)),
+        None => ret.push('\n'),
+    }
+    ret.push_str(&format!("Damage taken this run: {}\n", timeline.damage_taken_total()));
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture::*;
+    use libsts::{BossRelicChoice, CardChoice, DamageTaken, FloorKey};
+
+    fn save() -> Save {
+        let mut save = example_save();
+        save.floor_num = 18.0;
+        save.metric_path_per_floor = vec![Some("M".into()), Some("E".into()), None];
+        save.metric_gold_per_floor = vec![110, 135, 135];
+        save.metric_current_hp_per_floor = vec![66, 41, 41];
+        save.metric_max_hp_per_floor = vec![80, 80, 80];
+        let damage = |floor: f64, damage: f64, enemies: &str| DamageTaken { damage, enemies: enemies.into(), floor, turns: 3.0 };
+        save.metric_damage_taken = vec![damage(1.0, 6.0, "Jaw Worm"), damage(2.0, 25.0, "Gremlin Nob"), damage(18.0, 9.0, "Chosen")];
+        save.metric_card_choices = vec![
+            CardChoice { floor: 1.0, picked: "Inflame".into(), not_picked: vec!["Clash".into(), "Anger".into()] },
+            CardChoice { floor: 2.0, picked: "SKIP".into(), not_picked: vec!["Havoc".into()] },
+        ];
+        save.metric_relics_obtained = vec![FloorKey { floor: 2.0, key: "Vajra".into(), data: None }];
+        save.metric_boss_relics = vec![BossRelicChoice { picked: Some("Black Star".into()), not_picked: vec![] }];
+        save
+    }
+
+    #[test]
+    fn builds_records_for_each_floor() {
+        let timeline = RunTimeline::new(&save());
+        assert_eq!(timeline.floors.len(), 18);
+        assert_eq!(timeline.floors[1], FloorRecord {
+            floor: 2,
+            act: 1,
+            room: Some("E".into()),
+            gold: Some(135),
+            current_hp: Some(41),
+            max_hp: Some(80),
+            damage_taken: 25.0,
+            enemies: Some("Gremlin Nob".into()),
+            turns: Some(3.0),
+            card_picked: None,
+            cards_skipped: vec!["Havoc".into()],
+            relics: vec!["Vajra".into()],
+        });
+        assert_eq!(timeline.floors[16].relics, ["Black Star"]);
+        assert_eq!(timeline.floors[17].act, 2);
+    }
+
+    #[test]
+    fn summarizes_damage_per_act_and_exports_csv() {
+        let timeline = RunTimeline::new(&save());
+        assert_eq!(compose_txt_timeline(&timeline, 1),
+                   "Damage taken this act: 31; biggest hit: Gremlin Nob, floor 2 (25)\nDamage taken this run: 40\n");
+        assert_eq!(compose_txt_timeline(&timeline, 3), "Damage taken this act: 0\nDamage taken this run: 40\n");
+
+        let csv = compose_csv_timeline(&timeline);
+        assert_eq!(csv.lines().nth(1), Some("1,1,M,110,66,80,6,Jaw Worm,3,Inflame,Clash;Anger,"));
+        assert_eq!(csv_escape("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+}
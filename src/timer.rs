@@ -0,0 +1,139 @@
+// runのプレイ時間を扱うモジュール
+//
+// autosaveのplay_time(秒)は保存時にしか更新されないので、
+// 保存を検知した時刻からの経過時間を足して保存間の時間を補間する
+// フロアが進むたびに直前のフロアにかかった時間(スプリット)を記録する
+use libsts::Save;
+
+// 直近のスプリットとして表示する件数
+const SPLITS_SHOWN: usize = 3;
+// ゲームの起動を検出できないときに補間する上限(これ以上autosaveが更新されなければ止まっているとみなす)
+const UNDETECTED_IDLE_LIMIT_SECS: u64 = 600;
+
+// 秒をHH:MM:SS形式の文字列に変換する
+pub fn format_hms(secs: u64) -> String {
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+// フロアごとの所要時間
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Split {
+    pub floor: i64,
+    pub secs: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RunTimer {
+    seed: Option<i64>,
+    floor: i64,
+    play_time_secs: u64,      // 最後に検知したautosaveのplay_time
+    observed_secs: u64,       // そのautosaveを検知した時刻(UNIX時間の秒)
+    floor_started_secs: u64,  // 現在のフロアに入った時点のplay_time
+    splits: Vec<Split>,
+    ended: bool,
+}
+#[rustfmt::skip]
+impl RunTimer {
+    pub fn new() -> Self { Self::default() }
+    // getter
+    pub fn has_run(&self) -> bool { self.seed.is_some() }
+    pub fn splits(&self) -> &[Split] { &self.splits }
+
+    // 新しいautosaveのスナップショットを反映する(シードが変われば別のrunとして計測し直す)
+    pub fn update(&mut self, save: &Save, now_secs: u64) {
+        let floor = save.floor_num as i64;
+        let play_time_secs = save.play_time as u64;
+        if self.seed != Some(save.seed) {
+            *self = Self { seed: Some(save.seed), floor, floor_started_secs: play_time_secs, ..Self::default() };
+        } else if floor > self.floor {
+            let secs = play_time_secs.saturating_sub(self.floor_started_secs);
+            self.splits.push(Split { floor: self.floor, secs });
+            self.floor = floor;
+            self.floor_started_secs = play_time_secs;
+        }
+        self.play_time_secs = play_time_secs;
+        self.observed_secs = now_secs;
+        self.ended = false;
+    }
+    // runの終了(autosaveの削除)時点の時間で止める
+    pub fn stop(&mut self, now_secs: u64) {
+        if self.has_run() && !self.ended {
+            self.play_time_secs = self.elapsed_secs(now_secs, None);
+            self.observed_secs = now_secs;
+            self.ended = true;
+        }
+    }
+
+    // 現在のプレイ時間(ゲームが起動していなければ補間しない、起動を検出できなければ上限まで補間する)
+    pub fn elapsed_secs(&self, now_secs: u64, game_running: Option<bool>) -> u64 {
+        let idle_secs = now_secs.saturating_sub(self.observed_secs);
+        match game_running {
+            _ if self.ended => self.play_time_secs,
+            Some(false) => self.play_time_secs,
+            Some(true) => self.play_time_secs + idle_secs,
+            None => self.play_time_secs + idle_secs.min(UNDETECTED_IDLE_LIMIT_SECS),
+        }
+    }
+}
+
+/* sts_timer.txtの内容
+
+  Run time: 00:12:34
+  Floor 5: 00:01:23    <- 現在のフロア(進行中)
+  Floor 4: 00:02:10    <- 直近のスプリット(新しい順)
+*/
+pub fn compose_txt_timer(timer: &RunTimer, now_secs: u64, game_running: Option<bool>) -> String {
+    let elapsed_secs = timer.elapsed_secs(now_secs, game_running);
+    let mut ret = String::new();
+    if timer.ended {
+        ret.push_str(&format!("Run time: {} (ended)\n", format_hms(elapsed_secs)));
+    } else {
+        ret.push_str(&format!("Run time: {}\n", format_hms(elapsed_secs)));
+        let floor_secs = elapsed_secs.saturating_sub(timer.floor_started_secs);
+        ret.push_str(&format!("Floor {}: {}\n", timer.floor, format_hms(floor_secs)));
+    }
+    for split in timer.splits().iter().rev().take(SPLITS_SHOWN) {
+        ret.push_str(&format!("Floor {}: {}\n", split.floor, format_hms(split.secs)));
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::fixture::*;
+
+    fn save(floor: u32, play_time: u32) -> Save {
+        let json = AUTOSAVE.replace("\"floor_num\": 0.0", &format!("\"floor_num\": {}", floor))
+                           .replace("\"play_time\": 10", &format!("\"play_time\": {}", play_time));
+        Save::new(&json).unwrap()
+    }
+
+    #[test]
+    fn formats_hours_minutes_seconds() {
+        assert_eq!(format_hms(10), "00:00:10");
+        assert_eq!(format_hms(3725), "01:02:05");
+        assert_eq!(format_hms(360000), "100:00:00");
+    }
+
+    #[test]
+    fn interpolates_between_saves_and_records_splits() {
+        let mut timer = RunTimer::new();
+        timer.update(&save(0, 10), 1000);
+        assert_eq!(timer.elapsed_secs(1005, Some(true)), 15);
+        assert_eq!(timer.elapsed_secs(1005, Some(false)), 10);
+        assert_eq!(timer.elapsed_secs(1005, None), 15);
+        assert_eq!(timer.elapsed_secs(5000, None), 610);
+
+        timer.update(&save(1, 40), 1030);
+        timer.update(&save(1, 70), 1060);
+        timer.update(&save(2, 100), 1090);
+        assert_eq!(timer.splits(), [Split { floor: 0, secs: 30 }, Split { floor: 1, secs: 60 }]);
+        assert_eq!(compose_txt_timer(&timer, 1095, Some(true)),
+                   "Run time: 00:01:45\nFloor 2: 00:00:05\nFloor 1: 00:01:00\nFloor 0: 00:00:30\n");
+
+        timer.stop(1100);
+        assert_eq!(compose_txt_timer(&timer, 2000, Some(true)),
+                   "Run time: 00:01:50 (ended)\nFloor 1: 00:01:00\nFloor 0: 00:00:30\n");
+    }
+}